@@ -0,0 +1,506 @@
+use std::fmt;
+
+use linera_sdk::base::Amount;
+use prediction_market::{shares_for_amount, update_odds_after_trade, PRECISION};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    execution::{run_signals, Fill, Venue},
+    from_units,
+    metrics::{performance, PerformanceMetrics},
+    risk::RiskState,
+    strategy::StrategyContext,
+    to_units, BotConfig, BotError, BotPosition, BotStats, MarketSnapshot, Signal, TradeRecord,
+};
+
+/// Market id under which the replayed series is traded.
+const MARKET_ID: u64 = 0;
+
+/// One recorded observation of a market, odds as probabilities.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Tick {
+    pub timestamp: u64,
+    /// Traded volume, in tokens.
+    #[serde(default)]
+    pub volume: f64,
+    pub odds: Vec<f64>,
+}
+
+/// How simulated orders are filled against the recorded odds.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum FillModel {
+    /// Fills at the current odds and moves them with `update_odds_after_trade`.
+    MarketImpact { liquidity: u64 },
+    /// Fills against a constant-product pool seeded at the current odds.
+    ConstantProduct { liquidity: f64 },
+}
+
+impl Default for FillModel {
+    fn default() -> Self {
+        FillModel::MarketImpact {
+            liquidity: 1_000 * PRECISION,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BacktestConfig {
+    pub bot: BotConfig,
+    pub max_position_size: Amount,
+    #[serde(default)]
+    pub fill_model: FillModel,
+    /// Number of past ticks passed to the strategy as price history.
+    #[serde(default)]
+    pub history_len: usize,
+    /// Winning option, if the series ends with the market resolving.
+    #[serde(default)]
+    pub resolution: Option<u8>,
+}
+
+impl Default for BacktestConfig {
+    /// Momentum with 10-token orders, used when no configuration file is given.
+    fn default() -> Self {
+        BacktestConfig {
+            bot: BotConfig {
+                name: "backtest".into(),
+                trade_size: from_units(10 * PRECISION),
+                max_trades_per_block: 1,
+                is_active: true,
+                ..BotConfig::default()
+            },
+            max_position_size: from_units(100 * PRECISION),
+            fill_model: FillModel::default(),
+            history_len: 20,
+            resolution: None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum BacktestError {
+    #[error("line {line}: {message}")]
+    Csv { line: usize, message: String },
+    #[error("tick {index}: expected at least two odds")]
+    TooFewOdds { index: usize },
+    #[error("invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Parses `timestamp,volume,odds_0,odds_1,...` rows. A header row starting with
+/// `timestamp` and blank lines are skipped.
+pub fn parse_csv(input: &str) -> Result<Vec<Tick>, BacktestError> {
+    let mut ticks = Vec::new();
+    for (index, row) in input.lines().enumerate() {
+        let row = row.trim();
+        if row.is_empty() || row.starts_with("timestamp") {
+            continue;
+        }
+        let error = |message: String| BacktestError::Csv {
+            line: index + 1,
+            message,
+        };
+        let fields: Vec<&str> = row.split(',').map(str::trim).collect();
+        if fields.len() < 4 {
+            return Err(error(
+                "expected a timestamp, a volume and at least two odds".into(),
+            ));
+        }
+        let timestamp = fields[0]
+            .parse()
+            .map_err(|e| error(format!("timestamp: {e}")))?;
+        let volume = fields[1]
+            .parse()
+            .map_err(|e| error(format!("volume: {e}")))?;
+        let odds = fields[2..]
+            .iter()
+            .map(|field| field.parse().map_err(|e| error(format!("odds: {e}"))))
+            .collect::<Result<_, _>>()?;
+        ticks.push(Tick {
+            timestamp,
+            volume,
+            odds,
+        });
+    }
+    Ok(ticks)
+}
+
+/// Parses a JSON array of ticks, each with at least two odds.
+pub fn parse_json(input: &str) -> Result<Vec<Tick>, BacktestError> {
+    let ticks: Vec<Tick> = serde_json::from_str(input)?;
+    if let Some(index) = ticks.iter().position(|tick| tick.odds.len() < 2) {
+        return Err(BacktestError::TooFewOdds { index });
+    }
+    Ok(ticks)
+}
+
+pub struct BacktestReport {
+    pub trades: Vec<TradeRecord>,
+    pub blocked_orders: Vec<(u64, BotError)>,
+    pub positions: Vec<BotPosition>,
+    pub metrics: PerformanceMetrics,
+    pub stopped_at: Option<u64>,
+}
+
+/// Replays `ticks` through the configured strategy and risk checks.
+pub fn run(ticks: &[Tick], config: &BacktestConfig) -> BacktestReport {
+    let strategy = config.bot.strategy.strategy();
+    let mut stats = BotStats::default();
+    let mut risk_state = RiskState::default();
+    let mut trades = Vec::new();
+    let mut blocked_orders = Vec::new();
+    let mut stopped_at = None;
+
+    for (index, tick) in ticks.iter().enumerate() {
+        if stopped_at.is_some() {
+            break;
+        }
+        risk_state.roll_day(tick.timestamp);
+
+        let history = &ticks[index.saturating_sub(config.history_len)..index];
+        let snapshot = MarketSnapshot {
+            odds: tick.odds.clone(),
+            volume: from_units((tick.volume * PRECISION as f64) as u64),
+            price_history: history.iter().map(|t| t.odds.clone()).collect(),
+            updated_at: tick.timestamp,
+        };
+        let positions: Vec<(u8, Amount)> = stats
+            .current_positions
+            .iter()
+            .map(|p| (p.option_index, p.size()))
+            .collect();
+        let signals = strategy.signals(&StrategyContext {
+            market: &snapshot,
+            positions: &positions,
+            config: &config.bot,
+        });
+
+        let mut venue = Replay {
+            model: &config.fill_model,
+            odds: tick
+                .odds
+                .iter()
+                .map(|p| (p * PRECISION as f64) as u64)
+                .collect(),
+        };
+        let signals: Vec<(u64, Signal)> = signals
+            .into_iter()
+            .map(|signal| (MARKET_ID, signal))
+            .collect();
+        let max_trades = match config.bot.max_trades_per_block {
+            0 => u64::MAX,
+            per_block => per_block as u64,
+        };
+        let report = run_signals(
+            &signals,
+            &mut venue,
+            &config.bot,
+            config.max_position_size,
+            max_trades,
+            &mut stats,
+            &mut risk_state,
+            tick.timestamp,
+        );
+        stats.total_trades += report.trades.len() as u64;
+        trades.extend(report.trades);
+        blocked_orders.extend(
+            report
+                .blocked
+                .into_iter()
+                .map(|(_, _, error)| (tick.timestamp, error)),
+        );
+        if report.stopped {
+            stopped_at = Some(tick.timestamp);
+        }
+    }
+
+    // Settles the whole market in one record, as `Operation::SettleMarket` does.
+    if let (Some(winner), Some(last)) = (config.resolution, ticks.last()) {
+        let settled = std::mem::take(&mut stats.current_positions);
+        if !settled.is_empty() {
+            let payout: u64 = settled
+                .iter()
+                .filter(|p| p.option_index == winner)
+                .map(|p| p.shares)
+                .sum();
+            let cost: u64 = settled.iter().map(|p| p.cost).sum();
+            let pnl = payout as i64 - cost as i64;
+            stats.record_close(pnl);
+            trades.push(TradeRecord {
+                market_id: MARKET_ID,
+                option_index: settled[0].option_index,
+                amount: from_units(payout),
+                shares: settled.iter().map(|p| p.shares).sum(),
+                price: 0.0,
+                is_buy: false,
+                timestamp: last.timestamp,
+                pnl: Some(pnl),
+            });
+        }
+    }
+
+    let last_odds = ticks.last().map(|t| t.odds.clone()).unwrap_or_default();
+    let metrics = performance(&trades, &stats.current_positions, |_, option_index| {
+        last_odds
+            .get(option_index as usize)
+            .map(|p| (p * PRECISION as f64) as u64)
+    });
+
+    BacktestReport {
+        trades,
+        blocked_orders,
+        positions: stats.current_positions,
+        metrics,
+        stopped_at,
+    }
+}
+
+/// The replayed market at one tick, filled with the configured model. Orders are booked
+/// at the odds they were placed at.
+struct Replay<'a> {
+    model: &'a FillModel,
+    odds: Vec<u64>,
+}
+
+impl Venue for Replay<'_> {
+    fn price(&mut self, _market_id: u64, option_index: u8) -> Option<u64> {
+        self.odds.get(option_index as usize).copied()
+    }
+
+    fn fill(
+        &mut self,
+        _market_id: u64,
+        option_index: u8,
+        amount: u64,
+        is_buy: bool,
+    ) -> Result<Fill, BotError> {
+        let price = self.odds[option_index as usize];
+        let shares = fill(
+            self.model,
+            &mut self.odds,
+            option_index as usize,
+            amount,
+            is_buy,
+        )
+        .ok_or_else(|| BotError::OrderRejected("not enough liquidity".into()))?;
+        Ok(Fill {
+            shares,
+            collateral: amount,
+            price,
+        })
+    }
+}
+
+/// Fills `amount` of collateral on `option_index`, updating `odds`, and returns the shares
+/// bought or sold.
+fn fill(
+    model: &FillModel,
+    odds: &mut Vec<u64>,
+    option_index: usize,
+    amount: u64,
+    is_buy: bool,
+) -> Option<u64> {
+    match model {
+        FillModel::MarketImpact { liquidity } => {
+            let shares = shares_for_amount(amount, odds[option_index]);
+            *odds = update_odds_after_trade(odds, option_index, amount, is_buy, *liquidity);
+            Some(shares)
+        }
+        FillModel::ConstantProduct { liquidity } => {
+            // Reserves inversely proportional to price reproduce the current odds.
+            let mut reserves: Vec<f64> = odds
+                .iter()
+                .map(|p| liquidity * PRECISION as f64 / (*p).max(1) as f64)
+                .collect();
+            let amount = amount as f64;
+            let product: f64 = reserves.iter().product();
+            let others: f64 = reserves
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| *i != option_index)
+                .map(|(_, r)| if is_buy { r + amount } else { r - amount })
+                .product();
+            if others <= 0.0 {
+                return None;
+            }
+
+            // Collateral is added to (or removed from) every reserve, and the traded
+            // option's reserve absorbs the difference so the product is unchanged.
+            let shares = if is_buy {
+                reserves[option_index] + amount - product / others
+            } else {
+                product / others - reserves[option_index] + amount
+            };
+            for (i, reserve) in reserves.iter_mut().enumerate() {
+                *reserve += if is_buy { amount } else { -amount };
+                if i == option_index {
+                    *reserve += if is_buy { -shares } else { shares };
+                }
+            }
+
+            let inverse_total: f64 = reserves.iter().map(|r| 1.0 / r).sum();
+            *odds = reserves
+                .iter()
+                .map(|r| ((1.0 / r) / inverse_total * PRECISION as f64) as u64)
+                .collect();
+            Some(shares.max(0.0) as u64)
+        }
+    }
+}
+
+fn tokens(units: i64) -> String {
+    format!("{:.6}", units as f64 / PRECISION as f64)
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let metrics = &self.metrics;
+        writeln!(f, "Trades:          {}", self.trades.len())?;
+        writeln!(f, "Closed trades:   {}", metrics.closed_trades)?;
+        writeln!(
+            f,
+            "Win rate:        {:.2}%",
+            metrics.win_rate_bps as f64 / 100.0
+        )?;
+        writeln!(f, "Realised PnL:    {}", tokens(metrics.realised_pnl))?;
+        writeln!(f, "Unrealised PnL:  {}", tokens(metrics.unrealised_pnl))?;
+        writeln!(f, "Max drawdown:    {}", tokens(metrics.max_drawdown))?;
+        writeln!(
+            f,
+            "Sharpe ratio:    {:.3}",
+            metrics.sharpe_ratio as f64 / PRECISION as f64
+        )?;
+        writeln!(f, "Blocked orders:  {}", self.blocked_orders.len())?;
+        if let Some(at) = self.stopped_at {
+            writeln!(f, "Stopped by the daily loss limit at {at}")?;
+        }
+
+        writeln!(f)?;
+        writeln!(
+            f,
+            "{:>16}  {:>6}  {:>4}  {:>12}  {:>8}  {:>12}",
+            "timestamp", "option", "side", "amount", "price", "pnl"
+        )?;
+        for trade in &self.trades {
+            writeln!(
+                f,
+                "{:>16}  {:>6}  {:>4}  {:>12}  {:>8.4}  {:>12}",
+                trade.timestamp,
+                trade.option_index,
+                if trade.is_buy { "buy" } else { "sell" },
+                tokens(to_units(trade.amount) as i64),
+                trade.price,
+                trade.pnl.map(tokens).unwrap_or_default(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rising_series() -> Vec<Tick> {
+        parse_csv(
+            "timestamp,volume,odds_0,odds_1\n\
+             1,5.0,0.70,0.30\n\
+             2,5.0,0.75,0.25\n\
+             \n\
+             3,5.0,0.80,0.20\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn csv_skips_header_and_blank_lines() {
+        let ticks = rising_series();
+        assert_eq!(ticks.len(), 3);
+        assert_eq!(ticks[1].timestamp, 2);
+        assert_eq!(ticks[1].volume, 5.0);
+        assert_eq!(ticks[1].odds, vec![0.75, 0.25]);
+    }
+
+    #[test]
+    fn csv_reports_the_failing_line() {
+        match parse_csv("timestamp,volume,odds_0,odds_1\n1,5.0,0.5\n") {
+            Err(BacktestError::Csv { line, .. }) => assert_eq!(line, 2),
+            other => panic!("unexpected result: {:?}", other.map(|t| t.len())),
+        }
+        match parse_csv("1,5.0,0.5,abc\n") {
+            Err(BacktestError::Csv { line, message }) => {
+                assert_eq!(line, 1);
+                assert!(message.starts_with("odds"));
+            }
+            other => panic!("unexpected result: {:?}", other.map(|t| t.len())),
+        }
+    }
+
+    #[test]
+    fn json_requires_two_odds_per_tick() {
+        let ticks = parse_json(r#"[{"timestamp": 1, "odds": [0.6, 0.4]}]"#).unwrap();
+        assert_eq!(ticks[0].volume, 0.0);
+
+        let error = parse_json(
+            r#"[{"timestamp": 1, "odds": [0.6, 0.4]}, {"timestamp": 2, "odds": [1.0]}]"#,
+        )
+        .unwrap_err();
+        assert!(matches!(error, BacktestError::TooFewOdds { index: 1 }));
+    }
+
+    #[test]
+    fn market_impact_fills_at_current_odds() {
+        let model = FillModel::default();
+        let mut odds = vec![700_000, 300_000];
+        let amount = 10 * PRECISION;
+
+        let shares = fill(&model, &mut odds, 0, amount, true).unwrap();
+        assert_eq!(shares, shares_for_amount(amount, 700_000));
+        assert!(odds[0] > 700_000);
+    }
+
+    #[test]
+    fn constant_product_moves_the_traded_price() {
+        let model = FillModel::ConstantProduct {
+            liquidity: 1_000.0 * PRECISION as f64,
+        };
+        let amount = 10 * PRECISION;
+
+        let mut odds = vec![500_000, 500_000];
+        let bought = fill(&model, &mut odds, 0, amount, true).unwrap();
+        assert!(bought > amount && bought < 2 * amount);
+        assert!(odds[0] > 500_000);
+
+        let mut odds = vec![500_000, 500_000];
+        let sold = fill(&model, &mut odds, 0, amount, false).unwrap();
+        assert!(sold > bought);
+        assert!(odds[0] < 500_000);
+
+        let mut odds = vec![500_000, 500_000];
+        assert_eq!(fill(&model, &mut odds, 0, 3_000 * PRECISION, false), None);
+    }
+
+    #[test]
+    fn run_buys_the_rising_favourite_and_settles() {
+        let config = BacktestConfig {
+            resolution: Some(0),
+            ..BacktestConfig::default()
+        };
+        let report = run(&rising_series(), &config);
+
+        assert_eq!(report.trades.len(), 4);
+        for trade in &report.trades[..3] {
+            assert!(trade.is_buy);
+            assert_eq!(trade.option_index, 0);
+            assert_eq!(trade.amount, from_units(10 * PRECISION));
+        }
+        let settlement = &report.trades[3];
+        assert!(!settlement.is_buy);
+        assert!(settlement.pnl.unwrap() > 0);
+        assert!(report.positions.is_empty());
+        assert!(report.blocked_orders.is_empty());
+        assert_eq!(report.metrics.closed_trades, 1);
+        assert_eq!(report.metrics.realised_pnl, settlement.pnl.unwrap());
+        assert_eq!(report.stopped_at, None);
+    }
+}
@@ -0,0 +1,43 @@
+//! Replays a recorded price series through a bot configuration.
+//!
+//! Usage: `backtest <series.csv|series.json> [config.json]`
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    use std::{env, fs, process};
+
+    use trading_bot::backtest::{parse_csv, parse_json, run, BacktestConfig};
+
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 {
+        eprintln!("usage: {} <series.csv|series.json> [config.json]", args[0]);
+        process::exit(2);
+    }
+    let fail = |message: String| -> ! {
+        eprintln!("{message}");
+        process::exit(1);
+    };
+
+    let input = fs::read_to_string(&args[1])
+        .unwrap_or_else(|e| fail(format!("cannot read {}: {e}", args[1])));
+    let ticks = if args[1].ends_with(".json") {
+        parse_json(&input)
+    } else {
+        parse_csv(&input)
+    }
+    .unwrap_or_else(|e| fail(format!("{}: {e}", args[1])));
+
+    let config: BacktestConfig = match args.get(2) {
+        Some(path) => {
+            let raw = fs::read_to_string(path)
+                .unwrap_or_else(|e| fail(format!("cannot read {path}: {e}")));
+            serde_json::from_str(&raw).unwrap_or_else(|e| fail(format!("{path}: {e}")))
+        }
+        None => BacktestConfig::default(),
+    };
+
+    print!("{}", run(&ticks, &config));
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
@@ -0,0 +1,162 @@
+use linera_sdk::base::Amount;
+use prediction_market::PRECISION;
+
+use crate::{
+    from_units,
+    risk::{check_order, Order, RiskState},
+    to_units, BotConfig, BotError, BotStats, Signal, TradeAction, TradeRecord,
+    MIN_SIGNAL_CONFIDENCE,
+};
+
+/// Where orders are priced and filled: the prediction market, or a replayed series.
+pub trait Venue {
+    /// Current price of an option, scaled by `PRECISION`.
+    fn price(&mut self, market_id: u64, option_index: u8) -> Option<u64>;
+
+    /// Trades `amount` of collateral, in fixed-point units, on an option.
+    fn fill(
+        &mut self,
+        market_id: u64,
+        option_index: u8,
+        amount: u64,
+        is_buy: bool,
+    ) -> Result<Fill, BotError>;
+}
+
+/// A filled order. `collateral` is what a buy paid or a sell received, net of fees, in
+/// fixed-point units; `price` is scaled by `PRECISION`.
+pub struct Fill {
+    pub shares: u64,
+    pub collateral: u64,
+    pub price: u64,
+}
+
+/// What a run did: the trades booked, in order, and the orders that did not go through.
+#[derive(Default)]
+pub struct RunReport {
+    pub trades: Vec<TradeRecord>,
+    pub blocked: Vec<(u64, u8, BotError)>,
+    /// Set when the daily loss limit stopped the run.
+    pub stopped: bool,
+}
+
+/// Value of `shares` at `price`, in fixed-point units.
+pub fn shares_value(shares: u64, price: u64) -> u64 {
+    (shares as u128 * price as u128 / PRECISION as u128) as u64
+}
+
+/// Turns each `(market_id, signal)` into an order, checks it against the risk limits,
+/// fills it on `venue` and books the fill in `stats` and `risk_state`. At most
+/// `max_trades` orders are filled; the run stops at the daily loss limit. Trade history
+/// and `stats.total_trades` are left to the caller.
+#[allow(clippy::too_many_arguments)]
+pub fn run_signals(
+    signals: &[(u64, Signal)],
+    venue: &mut impl Venue,
+    config: &BotConfig,
+    max_position_size: Amount,
+    max_trades: u64,
+    stats: &mut BotStats,
+    risk_state: &mut RiskState,
+    now: u64,
+) -> RunReport {
+    let mut report = RunReport::default();
+    let mut traded_markets = Vec::new();
+
+    for (market_id, signal) in signals {
+        let (market_id, option_index) = (*market_id, signal.option_index);
+        if signal.confidence < MIN_SIGNAL_CONFIDENCE {
+            continue;
+        }
+        if report.trades.len() as u64 >= max_trades {
+            report
+                .blocked
+                .push((market_id, option_index, BotError::MaxTradesReached));
+            break;
+        }
+        let is_buy = match signal.action {
+            TradeAction::Buy => true,
+            TradeAction::Sell => false,
+            TradeAction::Hold => continue,
+        };
+        let Some(price) = venue.price(market_id, option_index) else {
+            report
+                .blocked
+                .push((market_id, option_index, BotError::MarketNotFound));
+            continue;
+        };
+
+        // Sells never exceed the value of the shares held; buys are bounded by the risk checks.
+        let mut position = stats.position(market_id, option_index);
+        let size = signal.size.unwrap_or(config.trade_size);
+        let size = if is_buy {
+            size
+        } else {
+            size.min(from_units(shares_value(position.shares, price)))
+        };
+        if size == Amount::ZERO {
+            continue;
+        }
+
+        let order = Order {
+            market_id,
+            option_index,
+            amount: size,
+            price: price as f64 / PRECISION as f64,
+            is_buy,
+        };
+        let amount = match check_order(&order, config, max_position_size, stats, risk_state, now) {
+            Ok(amount) => to_units(amount),
+            Err(error) => {
+                let stop = matches!(error, BotError::DailyLossLimit);
+                report.blocked.push((market_id, option_index, error));
+                if stop {
+                    report.stopped = true;
+                    break;
+                }
+                continue;
+            }
+        };
+
+        let fill = match venue.fill(market_id, option_index, amount, is_buy) {
+            Ok(fill) => fill,
+            Err(error) => {
+                report.blocked.push((market_id, option_index, error));
+                continue;
+            }
+        };
+        let pnl = if is_buy {
+            position.shares += fill.shares;
+            position.cost += fill.collateral;
+            None
+        } else {
+            let pnl = position.reduce(fill.shares, fill.collateral);
+            stats.record_close(pnl);
+            risk_state.record_pnl(pnl, now);
+            Some(pnl)
+        };
+        report.trades.push(TradeRecord {
+            market_id,
+            option_index,
+            amount: from_units(amount),
+            shares: fill.shares,
+            price: fill.price as f64 / PRECISION as f64,
+            is_buy,
+            timestamp: now,
+            pnl,
+        });
+        stats.save_position(position);
+        traded_markets.push(market_id);
+
+        if risk_state.loss_limit_hit(&config.risk) {
+            report.stopped = true;
+            break;
+        }
+    }
+
+    // The cooldown runs from the end of this run, so every leg of a signal gets through.
+    for market_id in traded_markets {
+        risk_state.record_trade(market_id, now);
+    }
+    report
+}
@@ -1,11 +1,40 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod backtest;
+pub mod execution;
+pub mod metrics;
+pub mod risk;
+pub mod schedule;
+pub mod strategy;
+
 use linera_sdk::{
-    base::{Amount, ApplicationId, ChainId, Owner},
-    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
+    base::{Amount, ApplicationId, ChainId, Owner, StreamName, StreamUpdate},
+    views::{linera_views, LogView, MapView, RegisterView, RootView, View, ViewStorageContext},
     Contract, ContractRuntime, Service, ServiceRuntime,
 };
+use async_graphql::{EmptySubscription, Object, Schema, SimpleObject};
+use metrics::{performance, PerformanceMetrics};
+use prediction_market::{
+    MarketEvent, Operation as MarketOperation, PredictionMarketAbi, MARKET_UPDATES_STREAM, PRECISION,
+};
+use execution::{run_signals, shares_value, Fill, Venue};
+use risk::{RiskLimits, RiskState};
+use schedule::{Moment, Schedule, ScheduleState};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use strategy::{
+    Arbitrage, Contrarian, GridMarketMaker, GridParams, MarketMaker, MeanReversion, MeanReversionParams,
+    Momentum, StrategyContext, TradingStrategy, ValueBetting, ValueBettingParams,
+};
 use thiserror::Error;
 
+/// Signals below this confidence are not traded.
+pub const MIN_SIGNAL_CONFIDENCE: f64 = 0.7;
+
+/// Page size of the `tradeHistory` query when `first` is omitted.
+const DEFAULT_TRADE_PAGE: usize = 50;
+/// Largest page the `tradeHistory` query returns.
+const MAX_TRADE_PAGE: usize = 500;
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct BotParameters {
     pub market_app_id: Option<ApplicationId>,
@@ -19,6 +48,23 @@ pub enum Strategy {
     Contrarian,
     Arbitrage,
     MarketMaker,
+    MeanReversion(MeanReversionParams),
+    ValueBetting(ValueBettingParams),
+    GridMarketMaker(GridParams),
+}
+
+impl Strategy {
+    pub fn strategy(&self) -> Box<dyn TradingStrategy> {
+        match self {
+            Strategy::Momentum => Box::new(Momentum),
+            Strategy::Contrarian => Box::new(Contrarian),
+            Strategy::Arbitrage => Box::new(Arbitrage),
+            Strategy::MarketMaker => Box::new(MarketMaker),
+            Strategy::MeanReversion(params) => Box::new(MeanReversion(params.clone())),
+            Strategy::ValueBetting(params) => Box::new(ValueBetting(params.clone())),
+            Strategy::GridMarketMaker(params) => Box::new(GridMarketMaker(params.clone())),
+        }
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -26,29 +72,140 @@ pub struct BotConfig {
     pub name: String,
     pub strategy: Strategy,
     pub trade_size: Amount,
+    /// Trades per run; zero is not enforced.
     pub max_trades_per_block: u8,
     pub min_odds_threshold: f64,
     pub max_odds_threshold: f64,
     pub is_active: bool,
+    #[serde(default)]
+    pub risk: RiskLimits,
+    #[serde(default)]
+    pub schedule: Schedule,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+async_graphql::scalar!(BotConfig);
+
+/// An entry of the configuration log: who changed the bot and its configuration afterwards.
+/// `changed_by` is unset for changes the bot makes itself, such as stopping at the loss limit.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct ConfigChange {
+    pub changed_by: Option<Owner>,
+    pub timestamp: u64,
+    pub action: String,
+    pub config: BotConfig,
+}
+
+/// A fill or settlement. `pnl` is the realised PnL of sells and settlements, in the
+/// market's fixed-point units. Settlements cover the whole market and carry `price` 0.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
 pub struct TradeRecord {
-    pub market_chain: ChainId,
+    pub market_id: u64,
     pub option_index: u8,
     pub amount: Amount,
+    pub shares: u64,
     pub price: f64,
     pub is_buy: bool,
     pub timestamp: u64,
-    pub pnl: Option<f64>,
+    pub pnl: Option<i64>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+/// An order stopped by the risk checks or rejected by the market.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct BlockedOrder {
+    pub market_id: u64,
+    pub option_index: u8,
+    pub reason: String,
+    pub timestamp: u64,
+}
+
+/// Shares held on one option and the collateral paid for them, in fixed-point units.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct BotPosition {
+    pub market_id: u64,
+    pub option_index: u8,
+    pub shares: u64,
+    pub cost: u64,
+}
+
+impl BotPosition {
+    /// Collateral committed to the position.
+    pub fn size(&self) -> Amount {
+        from_units(self.cost)
+    }
+    
+    /// Removes `shares` sold for `proceeds` and returns the realised PnL.
+    pub fn reduce(&mut self, shares: u64, proceeds: u64) -> i64 {
+        let sold = shares.min(self.shares);
+        let released = if sold == self.shares {
+            self.cost
+        } else {
+            (self.cost as u128 * sold as u128 / self.shares as u128) as u64
+        };
+        self.shares -= sold;
+        self.cost -= released;
+        proceeds as i64 - released as i64
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
 pub struct BotStats {
     pub total_trades: u64,
+    pub closed_trades: u64,
     pub winning_trades: u64,
-    pub total_pnl: f64,
-    pub current_positions: Vec<(ChainId, u8, Amount)>,
+    /// Realised PnL in fixed-point units.
+    pub total_pnl: i64,
+    pub current_positions: Vec<BotPosition>,
+}
+
+impl BotStats {
+    pub fn position(&self, market_id: u64, option_index: u8) -> BotPosition {
+        self.current_positions.iter()
+            .find(|p| p.market_id == market_id && p.option_index == option_index)
+            .cloned()
+            .unwrap_or(BotPosition {
+                market_id,
+                option_index,
+                ..BotPosition::default()
+            })
+    }
+    
+    /// Stores a position, dropping it once no shares are left.
+    pub fn save_position(&mut self, position: BotPosition) {
+        self.current_positions.retain(|p| !(p.market_id == position.market_id && p.option_index == position.option_index));
+        if position.shares > 0 {
+            self.current_positions.push(position);
+        }
+    }
+    
+    pub fn record_close(&mut self, pnl: i64) {
+        self.total_pnl += pnl;
+        self.closed_trades += 1;
+        if pnl > 0 {
+            self.winning_trades += 1;
+        }
+    }
+}
+
+/// Attos (10^-18 tokens) per fixed-point unit of the market.
+const ATTOS_PER_UNIT: u128 = 1_000_000_000_000_000_000 / PRECISION as u128;
+
+/// Converts a token amount to the market's fixed-point units, rounding down.
+pub fn to_units(amount: Amount) -> u64 {
+    u64::try_from(u128::from(amount) / ATTOS_PER_UNIT).unwrap_or(u64::MAX)
+}
+
+pub fn from_units(units: u64) -> Amount {
+    Amount::from_attos(u128::from(units) * ATTOS_PER_UNIT)
+}
+
+/// Market data last fetched from the prediction-market application, prices as probabilities.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct MarketSnapshot {
+    pub odds: Vec<f64>,
+    pub volume: Amount,
+    /// Odds after each recent trade, oldest first.
+    pub price_history: Vec<Vec<f64>>,
+    pub updated_at: u64,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -59,29 +216,75 @@ pub enum Operation {
     Execute,
     Stop,
     Start,
+    /// Fetches a market from the prediction-market application and stores a signal for it.
     AnalyzeMarket {
-        market_chain: ChainId,
-        odds: Vec<f64>,
+        market_id: u64,
+    },
+    /// Analyzes every market on the watchlist.
+    AnalyzeWatchlist,
+    WatchMarket {
+        market_id: u64,
     },
+    UnwatchMarket {
+        market_id: u64,
+    },
+    /// Sells the whole position at the current price.
     ClosePosition {
-        market_chain: ChainId,
+        market_id: u64,
         option_index: u8,
     },
+    /// Claims the payout of a resolved market and realises PnL on its positions.
+    SettleMarket {
+        market_id: u64,
+    },
+    /// Lets `operator` configure, start, stop and run the bot.
+    AddOperator {
+        operator: Owner,
+    },
+    RemoveOperator {
+        operator: Owner,
+    },
+    TransferOwnership {
+        new_owner: Owner,
+    },
+    /// Runs the bot on the market updates published on `chain_id`, following
+    /// `BotConfig.schedule`.
+    SubscribeMarketUpdates {
+        chain_id: ChainId,
+    },
+    UnsubscribeMarketUpdates {
+        chain_id: ChainId,
+    },
+}
+
+impl Operation {
+    /// Operations reserved to the owner: payouts and access management. Operators may
+    /// submit everything else.
+    pub fn owner_only(&self) -> bool {
+        matches!(
+            self,
+            Operation::SettleMarket { .. }
+                | Operation::AddOperator { .. }
+                | Operation::RemoveOperator { .. }
+                | Operation::TransferOwnership { .. }
+        )
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Message {
     TradeSignal {
-        market: ChainId,
+        market: u64,
         option_index: u8,
         action: TradeAction,
         confidence: f64,
     },
+    /// Sent to the bot's own chain when a position is reduced or settled.
     PositionUpdate {
-        market: ChainId,
+        market: u64,
         option_index: u8,
         size: Amount,
-        pnl: f64,
+        pnl: i64,
     },
     BotExecuted {
         trades_made: u64,
@@ -89,13 +292,23 @@ pub enum Message {
     },
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, async_graphql::Enum)]
 pub enum TradeAction {
     Buy,
     Sell,
     Hold,
 }
 
+/// One leg of a signal: the side to take on a single option.
+#[derive(Debug, Clone, Deserialize, Serialize, SimpleObject)]
+pub struct Signal {
+    pub option_index: u8,
+    pub action: TradeAction,
+    pub confidence: f64,
+    /// Order size chosen by the strategy; `BotConfig.trade_size` when unset.
+    pub size: Option<Amount>,
+}
+
 #[derive(Debug, Error)]
 pub enum BotError {
     #[error("Bot is not active")]
@@ -108,15 +321,37 @@ pub enum BotError {
     PositionSizeExceeded,
     #[error("Market not found")]
     MarketNotFound,
+    #[error("Price outside the configured odds band")]
+    OddsOutOfRange,
+    #[error("Market is cooling down after the last order")]
+    Cooldown,
+    #[error("Daily loss limit reached")]
+    DailyLossLimit,
+    #[error("No open position")]
+    NoPosition,
+    #[error("Market rejected the order: {0}")]
+    OrderRejected(String),
+    #[error("Signer is neither the owner nor an operator of this bot")]
+    Unauthorized,
+    #[error("Only the bot owner can do this")]
+    OwnerOnly,
 }
 
-#[derive(RootView, async_graphql::SimpleObject)]
+#[derive(RootView, SimpleObject)]
 #[view(context = "ViewStorageContext")]
 pub struct BotView {
     pub config: RegisterView<BotConfig>,
     pub stats: RegisterView<BotStats>,
     pub trade_history: MapView<u64, TradeRecord>,
-    pub market_signals: MapView<ChainId, (TradeAction, f64)>,
+    pub market_signals: MapView<(u64, u8), Signal>,
+    pub watchlist: MapView<u64, MarketSnapshot>,
+    pub risk_state: RegisterView<RiskState>,
+    /// The instantiating signer, until ownership is transferred.
+    pub owner: RegisterView<Option<Owner>>,
+    pub operators: MapView<Owner, ()>,
+    pub config_log: LogView<ConfigChange>,
+    pub schedule_state: RegisterView<ScheduleState>,
+    pub blocked_orders: LogView<BlockedOrder>,
 }
 
 pub struct BotContract {
@@ -133,92 +368,161 @@ impl Contract for BotContract {
     }
 
     async fn instantiate(&mut self, config: Self::InstantiationArgument) {
+        let owner = self.runtime.authenticated_signer()
+            .expect("A trading bot must be instantiated by a signer, who owns it");
+        self.runtime.state().owner.set(Some(owner));
         self.runtime.state().config.set(config);
         self.runtime.state().stats.set(BotStats::default());
+        self.log_change(Some(owner), "Instantiate".to_string());
     }
 
     async fn execute_operation(&mut self, operation: Operation) -> Result<(), BotError> {
+        let signer = self.authorize(operation.owner_only()).await?;
         match operation {
             Operation::Configure { config } => {
                 self.runtime.state().config.set(config);
+                self.log_change(signer, "Configure".to_string());
+                Ok(())
+            }
+            
+            Operation::Execute => self.execute(None, u64::MAX).await.map(|_| ()),
+            
+            Operation::Stop => {
+                self.stop(signer);
+                Ok(())
+            }
+            
+            Operation::Start => {
+                let mut config = self.runtime.state().config.get().clone();
+                config.is_active = true;
+                self.runtime.state().config.set(config);
+                self.log_change(signer, "Start".to_string());
                 Ok(())
             }
             
-            Operation::Execute => {
-                let config = self.runtime.state().config.get();
-                if !config.is_active {
-                    return Err(BotError::NotActive);
+            Operation::AnalyzeMarket { market_id } => {
+                self.analyze_market(market_id).await
+            }
+            
+            Operation::AnalyzeWatchlist => {
+                let watched = self.runtime.state().watchlist.indices().await.unwrap_or_default();
+                for market_id in watched {
+                    // A market that can no longer be fetched keeps its last snapshot.
+                    let _ = self.analyze_market(market_id).await;
+                }
+                Ok(())
+            }
+            
+            Operation::WatchMarket { market_id } => {
+                let snapshot = self.fetch_market(market_id)?;
+                self.runtime.state().watchlist.insert(&market_id, snapshot);
+                Ok(())
+            }
+            
+            Operation::UnwatchMarket { market_id } => {
+                self.runtime.state().watchlist.remove(&market_id);
+                self.clear_signals(market_id).await;
+                Ok(())
+            }
+            
+            Operation::ClosePosition { market_id, option_index } => {
+                let mut stats = self.runtime.state().stats.get().clone();
+                let mut position = stats.position(market_id, option_index);
+                if position.shares == 0 {
+                    return Err(BotError::NoPosition);
                 }
                 
-                let signals: Vec<_> = vec![];
-                let mut trades_made = 0u64;
+                let price = self.price(market_id, option_index).ok_or(BotError::MarketNotFound)?;
+                let proceeds = shares_value(position.shares, price);
+                let fill = self.fill(market_id, option_index, proceeds, false)?;
                 
-                for (market, (action, confidence)) in signals {
-                    if trades_made >= config.max_trades_per_block as u64 {
-                        break;
-                    }
-                    
-                    if confidence >= 0.7 {
-                        match action {
-                            TradeAction::Buy => {
-                                trades_made += 1;
-                            }
-                            TradeAction::Sell => {
-                                trades_made += 1;
-                            }
-                            TradeAction::Hold => {}
-                        }
-                    }
+                let shares = fill.shares.min(position.shares);
+                let pnl = position.reduce(shares, fill.collateral);
+                let now = self.runtime.system_time().micros();
+                self.record_trade(&mut stats, TradeRecord {
+                    market_id,
+                    option_index,
+                    amount: from_units(proceeds),
+                    shares,
+                    price: fill.price as f64 / PRECISION as f64,
+                    is_buy: false,
+                    timestamp: now,
+                    pnl: Some(pnl),
+                });
+                stats.save_position(position.clone());
+                self.realise(&mut stats, pnl, now);
+                self.notify_position(&position, pnl);
+                Ok(())
+            }
+            
+            Operation::SettleMarket { market_id } => {
+                let market_app = self.market_app()?;
+                let response = self.runtime.call_application(
+                    true,
+                    market_app,
+                    &MarketOperation::ClaimPayout { market_id },
+                );
+                if !response.success {
+                    return Err(BotError::OrderRejected(response.message));
                 }
                 
                 let mut stats = self.runtime.state().stats.get().clone();
-                stats.total_trades += trades_made;
-                self.runtime.state().stats.set(stats);
-                
-                self.runtime.prepare_message(Message::BotExecuted {
-                    trades_made,
-                    markets_analyzed: signals.len() as u64,
-                }).send_to(self.runtime.chain_id());
+                let (settled, open): (Vec<BotPosition>, Vec<BotPosition>) = stats.current_positions
+                    .drain(..)
+                    .partition(|p| p.market_id == market_id);
+                stats.current_positions = open;
+                if settled.is_empty() {
+                    return Err(BotError::NoPosition);
+                }
                 
+                let payout = response.payout.unwrap_or_default();
+                let cost: u64 = settled.iter().map(|p| p.cost).sum();
+                let pnl = payout as i64 - cost as i64;
+                let now = self.runtime.system_time().micros();
+                self.record_trade(&mut stats, TradeRecord {
+                    market_id,
+                    option_index: settled[0].option_index,
+                    amount: from_units(payout),
+                    shares: settled.iter().map(|p| p.shares).sum(),
+                    price: 0.0,
+                    is_buy: false,
+                    timestamp: now,
+                    pnl: Some(pnl),
+                });
+                self.realise(&mut stats, pnl, now);
+                for position in settled {
+                    self.notify_position(&BotPosition { shares: 0, cost: 0, ..position }, pnl);
+                }
                 Ok(())
             }
             
-            Operation::Stop => {
-                let mut config = self.runtime.state().config.get().clone();
-                config.is_active = false;
-                self.runtime.state().config.set(config);
+            Operation::AddOperator { operator } => {
+                self.runtime.state().operators.insert(&operator, ());
+                self.log_change(signer, format!("AddOperator {operator}"));
                 Ok(())
             }
             
-            Operation::Start => {
-                let mut config = self.runtime.state().config.get().clone();
-                config.is_active = true;
-                self.runtime.state().config.set(config);
+            Operation::RemoveOperator { operator } => {
+                self.runtime.state().operators.remove(&operator);
+                self.log_change(signer, format!("RemoveOperator {operator}"));
                 Ok(())
             }
             
-            Operation::AnalyzeMarket { market_chain, odds } => {
-                let config = self.runtime.state().config.get();
-                let signal = self.generate_signal(&config.strategy, &odds);
-                
-                self.runtime.state().market_signals.insert(&market_chain, signal.clone());
-                
-                self.runtime.prepare_message(Message::TradeSignal {
-                    market: market_chain,
-                    option_index: 0,
-                    action: signal.0,
-                    confidence: signal.1,
-                }).send_to(self.runtime.chain_id());
-                
+            Operation::TransferOwnership { new_owner } => {
+                self.runtime.state().owner.set(Some(new_owner));
+                self.log_change(signer, format!("TransferOwnership {new_owner}"));
                 Ok(())
             }
             
-            Operation::ClosePosition { market_chain, option_index } => {
-                let mut stats = self.runtime.state().stats.get().clone();
-                stats.current_positions.retain(|(m, o, _)| 
-                    !(*m == market_chain && *o == option_index)
-                );
-                self.runtime.state().stats.set(stats);
+            Operation::SubscribeMarketUpdates { chain_id } => {
+                let market_app = self.market_app()?.forget_abi();
+                self.runtime.subscribe_to_events(chain_id, market_app, StreamName(MARKET_UPDATES_STREAM.to_vec()));
+                Ok(())
+            }
+            
+            Operation::UnsubscribeMarketUpdates { chain_id } => {
+                let market_app = self.market_app()?.forget_abi();
+                self.runtime.unsubscribe_from_events(chain_id, market_app, StreamName(MARKET_UPDATES_STREAM.to_vec()));
                 Ok(())
             }
         }
@@ -226,77 +530,470 @@ impl Contract for BotContract {
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            Message::TradeSignal { market, option_index, action, confidence } => {}
-            Message::PositionUpdate { market, option_index, size, pnl } => {
-                let mut stats = self.runtime.state().stats.get().clone();
-                stats.total_pnl += pnl;
-                if pnl > 0.0 {
-                    stats.winning_trades += 1;
+            Message::TradeSignal { .. } => {}
+            Message::PositionUpdate { .. } => {}
+            Message::BotExecuted { .. } => {}
+        }
+    }
+
+    async fn process_streams(&mut self, updates: Vec<StreamUpdate>) {
+        for update in updates {
+            for index in update.new_indices() {
+                let event: MarketEvent = self.runtime.read_event(
+                    update.chain_id,
+                    update.stream_id.stream_name.clone(),
+                    index,
+                );
+                if let MarketEvent::Updated(market_update) = event {
+                    self.run_scheduled(market_update.market_id).await;
                 }
-                self.runtime.state().stats.set(stats);
             }
-            Message::BotExecuted { trades_made, markets_analyzed } => {}
         }
     }
 }
 
 impl BotContract {
-    fn generate_signal(&self, strategy: &Strategy, odds: &[f64]) -> (TradeAction, f64) {
-        match strategy {
-            Strategy::Momentum => {
-                let max_odds = odds.iter().cloned().fold(0.0f64, f64::max);
-                if max_odds > 0.6 {
-                    (TradeAction::Buy, max_odds)
-                } else {
-                    (TradeAction::Hold, 0.5)
-                }
-            }
-            
-            Strategy::Contrarian => {
-                let min_odds = odds.iter().cloned().fold(1.0f64, f64::min);
-                if min_odds < 0.3 {
-                    (TradeAction::Buy, 1.0 - min_odds)
-                } else {
-                    (TradeAction::Hold, 0.5)
-                }
+    /// Trades the stored signals, only those of `market` when set, making at most
+    /// `max_trades` trades and no more than `max_trades_per_block`. Returns the trades made.
+    async fn execute(&mut self, market: Option<u64>, max_trades: u64) -> Result<u64, BotError> {
+        let config = self.runtime.state().config.get().clone();
+        if !config.is_active {
+            return Err(BotError::NotActive);
+        }
+        
+        let params = self.runtime.application_parameters();
+        self.market_app()?;
+        
+        let mut signals = Vec::new();
+        let keys = self.runtime.state().market_signals.indices().await.unwrap_or_default();
+        for key in keys.into_iter().filter(|(m, _)| market.is_none_or(|market| *m == market)) {
+            if let Ok(Some(signal)) = self.runtime.state().market_signals.get(&key).await {
+                signals.push((key.0, signal));
             }
-            
-            Strategy::Arbitrage => {
-                let total: f64 = odds.iter().sum();
-                if total < 0.95 {
-                    (TradeAction::Buy, 0.95 - total + 0.5)
-                } else if total > 1.05 {
-                    (TradeAction::Sell, total - 1.05 + 0.5)
-                } else {
-                    (TradeAction::Hold, 0.5)
-                }
+        }
+        
+        let mut stats = self.runtime.state().stats.get().clone();
+        let mut risk_state = self.runtime.state().risk_state.get().clone();
+        let now = self.runtime.system_time().micros();
+        risk_state.roll_day(now);
+        let max_trades = match config.max_trades_per_block {
+            0 => max_trades,
+            per_block => max_trades.min(per_block as u64),
+        };
+        let report = run_signals(&signals, self, &config, params.max_position_size, max_trades, &mut stats, &mut risk_state, now);
+        
+        for (market_id, option_index, error) in report.blocked {
+            self.report_blocked(market_id, option_index, error);
+        }
+        let trades_made = report.trades.len() as u64;
+        for trade in report.trades {
+            let (market_id, option_index) = (trade.market_id, trade.option_index);
+            if let Some(pnl) = trade.pnl {
+                self.notify_position(&stats.position(market_id, option_index), pnl);
             }
+            self.record_trade(&mut stats, trade);
+            self.runtime.state().market_signals.remove(&(market_id, option_index));
+        }
+        
+        if report.stopped {
+            self.stop(None);
+        }
+        self.runtime.state().stats.set(stats);
+        self.runtime.state().risk_state.set(risk_state);
+        
+        self.runtime.prepare_message(Message::BotExecuted {
+            trades_made,
+            markets_analyzed: signals.len() as u64,
+        }).send_to(self.runtime.chain_id());
+        
+        Ok(trades_made)
+    }
+    
+    /// Analyzes and trades a watched market after an update, within the schedule's
+    /// interval and trade budget. No signer is authenticated here; the market books the
+    /// trades under this application, like those of `Execute`.
+    async fn run_scheduled(&mut self, market_id: u64) {
+        let config = self.runtime.state().config.get().clone();
+        if !config.is_active || !config.schedule.is_enabled() {
+            return;
+        }
+        let watched = self.runtime.state().watchlist.contains_key(&market_id).await.unwrap_or(false);
+        if !watched {
+            return;
+        }
+        
+        let now = Moment {
+            height: self.runtime.block_height().0,
+            micros: self.runtime.system_time().micros(),
+        };
+        let mut schedule_state = self.runtime.state().schedule_state.get().clone();
+        let allowance = schedule_state.allowance(&config.schedule, market_id, now);
+        if allowance == 0 {
+            return;
+        }
+        
+        // A market that can no longer be fetched is retried on its next update.
+        if self.analyze_market(market_id).await.is_err() {
+            return;
+        }
+        let trades = self.execute(Some(market_id), allowance).await.unwrap_or(0);
+        schedule_state.record_run(market_id, now, trades);
+        self.runtime.state().schedule_state.set(schedule_state);
+    }
+    
+    /// Checks the authenticated signer against the owner and, unless `owner_only`, the
+    /// operators, and returns it.
+    async fn authorize(&mut self, owner_only: bool) -> Result<Option<Owner>, BotError> {
+        let signer = self.runtime.authenticated_signer().ok_or(BotError::Unauthorized)?;
+        if *self.runtime.state().owner.get() == Some(signer) {
+            return Ok(Some(signer));
+        }
+        let is_operator = self.runtime.state().operators.contains_key(&signer).await.unwrap_or(false);
+        match (is_operator, owner_only) {
+            (true, false) => Ok(Some(signer)),
+            (true, true) => Err(BotError::OwnerOnly),
+            (false, _) => Err(BotError::Unauthorized),
+        }
+    }
+    
+    fn log_change(&mut self, changed_by: Option<Owner>, action: String) {
+        let change = ConfigChange {
+            changed_by,
+            timestamp: self.runtime.system_time().micros(),
+            action,
+            config: self.runtime.state().config.get().clone(),
+        };
+        self.runtime.state().config_log.push(change);
+    }
+    
+    /// Deactivates the bot; `changed_by` is unset when the risk checks stop it.
+    fn stop(&mut self, changed_by: Option<Owner>) {
+        let mut config = self.runtime.state().config.get().clone();
+        config.is_active = false;
+        self.runtime.state().config.set(config);
+        self.log_change(changed_by, "Stop".to_string());
+    }
+    
+    /// Books realised PnL from a close or settlement, stopping the bot at the daily loss limit.
+    fn realise(&mut self, stats: &mut BotStats, pnl: i64, now: u64) {
+        stats.record_close(pnl);
+        self.runtime.state().stats.set(stats.clone());
+        
+        let mut risk_state = self.runtime.state().risk_state.get().clone();
+        risk_state.record_pnl(pnl, now);
+        let config = self.runtime.state().config.get().clone();
+        if config.is_active && risk_state.loss_limit_hit(&config.risk) {
+            self.stop(None);
+        }
+        self.runtime.state().risk_state.set(risk_state);
+    }
+    
+    fn record_trade(&mut self, stats: &mut BotStats, record: TradeRecord) {
+        self.runtime.state().trade_history.insert(&stats.total_trades, record);
+        stats.total_trades += 1;
+    }
+    
+    fn notify_position(&mut self, position: &BotPosition, pnl: i64) {
+        self.runtime.prepare_message(Message::PositionUpdate {
+            market: position.market_id,
+            option_index: position.option_index,
+            size: position.size(),
+            pnl,
+        }).send_to(self.runtime.chain_id());
+    }
+    
+    fn report_blocked(&mut self, market_id: u64, option_index: u8, error: BotError) {
+        let timestamp = self.runtime.system_time().micros();
+        self.runtime.state().blocked_orders.push(BlockedOrder {
+            market_id,
+            option_index,
+            reason: error.to_string(),
+            timestamp,
+        });
+    }
+    
+    fn market_app(&mut self) -> Result<ApplicationId<PredictionMarketAbi>, BotError> {
+        self.runtime.application_parameters()
+            .market_app_id
+            .map(|id| id.with_abi::<PredictionMarketAbi>())
+            .ok_or(BotError::MarketNotFound)
+    }
+    
+    fn fetch_market(&mut self, market_id: u64) -> Result<MarketSnapshot, BotError> {
+        let market_app = self.market_app()?;
+        let response = self.runtime.call_application(
+            true,
+            market_app,
+            &MarketOperation::GetMarketData { market_id },
+        );
+        let data = response.market_data.ok_or(BotError::MarketNotFound)?;
+        
+        let probabilities = |odds: &[u64]| odds.iter().map(|p| *p as f64 / PRECISION as f64).collect::<Vec<_>>();
+        Ok(MarketSnapshot {
+            odds: probabilities(&data.odds),
+            volume: from_units(data.total_volume),
+            price_history: data.price_history.iter().map(|point| probabilities(&point.odds)).collect(),
+            updated_at: self.runtime.system_time().micros(),
+        })
+    }
+    
+    /// Refreshes a market from the prediction-market application and stores a signal for it.
+    async fn analyze_market(&mut self, market_id: u64) -> Result<(), BotError> {
+        let snapshot = self.fetch_market(market_id)?;
+        let watched = self.runtime.state().watchlist.contains_key(&market_id).await.unwrap_or(false);
+        if watched {
+            self.runtime.state().watchlist.insert(&market_id, snapshot.clone());
+        }
+        
+        let config = self.runtime.state().config.get().clone();
+        let positions: Vec<(u8, Amount)> = self.runtime.state().stats.get().current_positions.iter()
+            .filter(|p| p.market_id == market_id)
+            .map(|p| (p.option_index, p.size()))
+            .collect();
+        let context = StrategyContext {
+            market: &snapshot,
+            positions: &positions,
+            config: &config,
+        };
+        let signals = config.strategy.strategy().signals(&context);
+        
+        self.clear_signals(market_id).await;
+        for signal in signals {
+            self.runtime.state().market_signals.insert(&(market_id, signal.option_index), signal.clone());
             
-            Strategy::MarketMaker => {
-                let spread = odds.iter().cloned().fold(0.0f64, f64::max) - 
-                            odds.iter().cloned().fold(1.0f64, f64::min);
-                if spread > 0.2 {
-                    (TradeAction::Buy, 0.5 + spread)
-                } else {
-                    (TradeAction::Hold, 0.5)
-                }
-            }
+            self.runtime.prepare_message(Message::TradeSignal {
+                market: market_id,
+                option_index: signal.option_index,
+                action: signal.action,
+                confidence: signal.confidence,
+            }).send_to(self.runtime.chain_id());
+        }
+        
+        Ok(())
+    }
+    
+    async fn clear_signals(&mut self, market_id: u64) {
+        let keys = self.runtime.state().market_signals.indices().await.unwrap_or_default();
+        for key in keys.into_iter().filter(|(m, _)| *m == market_id) {
+            self.runtime.state().market_signals.remove(&key);
+        }
+    }
+}
+
+/// Orders go to the prediction-market application, which books them under this application.
+impl Venue for BotContract {
+    fn price(&mut self, market_id: u64, option_index: u8) -> Option<u64> {
+        let market_app = self.market_app().ok()?;
+        let response = self.runtime.call_application(
+            true,
+            market_app,
+            &MarketOperation::GetMarketData { market_id },
+        );
+        response.market_data?.odds.get(option_index as usize).copied()
+    }
+    
+    fn fill(&mut self, market_id: u64, option_index: u8, amount: u64, is_buy: bool) -> Result<Fill, BotError> {
+        let market_app = self.market_app()?;
+        let response = self.runtime.call_application(
+            true,
+            market_app,
+            &MarketOperation::PlaceTrade {
+                market_id,
+                option_index,
+                amount,
+                is_buy,
+            },
+        );
+        if !response.success {
+            return Err(BotError::OrderRejected(response.message));
         }
+        // Sells are booked at what the market paid out, net of fees.
+        Ok(Fill {
+            shares: response.shares.unwrap_or_default(),
+            collateral: if is_buy { amount } else { response.payout.unwrap_or_default() },
+            price: response.price.unwrap_or_default(),
+        })
     }
 }
 
 pub struct BotService {
-    runtime: ServiceRuntime<Self>,
+    state: Arc<BotView>,
+    runtime: Arc<ServiceRuntime<Self>>,
 }
 
 impl Service for BotService {
     type Parameters = BotParameters;
 
     async fn load(runtime: ServiceRuntime<Self>) -> Self {
-        Self { runtime }
+        let state = BotView::load(runtime.root_view_storage_context())
+            .await
+            .expect("Failed to load state");
+        Self {
+            state: Arc::new(state),
+            runtime: Arc::new(runtime),
+        }
     }
 
     async fn handle_query(&self, query: Self::Query) -> Self::QueryResponse {
-        query
+        let schema = Schema::build(
+            BotQueryRoot {
+                state: self.state.clone(),
+            },
+            BotMutationRoot {
+                runtime: self.runtime.clone(),
+            },
+            EmptySubscription,
+        )
+        .finish();
+        schema.execute(query).await
+    }
+}
+
+/// A trade together with its position in the trade history, usable as a cursor.
+#[derive(SimpleObject)]
+struct TradeEntry {
+    index: u64,
+    trade: TradeRecord,
+}
+
+#[derive(SimpleObject)]
+struct MarketSignal {
+    market_id: u64,
+    signal: Signal,
+}
+
+struct BotQueryRoot {
+    state: Arc<BotView>,
+}
+
+#[Object]
+impl BotQueryRoot {
+    async fn config(&self) -> BotConfig {
+        self.state.config.get().clone()
+    }
+    
+    async fn stats(&self) -> BotStats {
+        self.state.stats.get().clone()
     }
+    
+    async fn positions(&self) -> Vec<BotPosition> {
+        self.state.stats.get().current_positions.clone()
+    }
+    
+    /// Trades in execution order, starting after the trade at index `after`. Pages hold
+    /// `first` trades, 50 by default and at most 500.
+    async fn trade_history(&self, first: Option<usize>, after: Option<u64>) -> Vec<TradeEntry> {
+        let first = first.unwrap_or(DEFAULT_TRADE_PAGE).min(MAX_TRADE_PAGE);
+        let start = after.map_or(0, |index| index + 1);
+        let end = self.state.stats.get().total_trades;
+        let mut entries = Vec::new();
+        for index in start..end {
+            if entries.len() >= first {
+                break;
+            }
+            if let Ok(Some(trade)) = self.state.trade_history.get(&index).await {
+                entries.push(TradeEntry { index, trade });
+            }
+        }
+        entries
+    }
+    
+    /// Pending signals, ordered by market and option.
+    async fn signals(&self) -> Vec<MarketSignal> {
+        let mut keys = self.state.market_signals.indices().await.unwrap_or_default();
+        keys.sort();
+        let mut signals = Vec::new();
+        for key in keys {
+            if let Ok(Some(signal)) = self.state.market_signals.get(&key).await {
+                signals.push(MarketSignal { market_id: key.0, signal });
+            }
+        }
+        signals
+    }
+    
+    async fn owner(&self) -> Option<Owner> {
+        *self.state.owner.get()
+    }
+    
+    async fn operators(&self) -> Vec<Owner> {
+        self.state.operators.indices().await.unwrap_or_default()
+    }
+    
+    async fn config_log(&self) -> Vec<ConfigChange> {
+        let count = self.state.config_log.count();
+        self.state.config_log.read(0..count).await.unwrap_or_default()
+    }
+    
+    /// Orders stopped by the risk checks or rejected by the market, oldest first, starting
+    /// at index `after` + 1. Pages hold `first` orders, 50 by default and at most 500.
+    async fn blocked_orders(&self, first: Option<usize>, after: Option<usize>) -> Vec<BlockedOrder> {
+        let first = first.unwrap_or(DEFAULT_TRADE_PAGE).min(MAX_TRADE_PAGE);
+        let start = after.map_or(0, |index| index + 1);
+        let end = self.state.blocked_orders.count().min(start.saturating_add(first));
+        if start >= end {
+            return Vec::new();
+        }
+        self.state.blocked_orders.read(start..end).await.unwrap_or_default()
+    }
+    
+    /// Realised and unrealised PnL and performance ratios, with open positions marked
+    /// at the odds last fetched for the watchlist.
+    async fn metrics(&self) -> PerformanceMetrics {
+        let total_trades = self.state.stats.get().total_trades;
+        let mut trades = Vec::new();
+        for index in 0..total_trades {
+            if let Ok(Some(trade)) = self.state.trade_history.get(&index).await {
+                trades.push(trade);
+            }
+        }
+        
+        let positions = self.state.stats.get().current_positions.clone();
+        let mut prices = Vec::new();
+        for position in &positions {
+            if let Ok(Some(snapshot)) = self.state.watchlist.get(&position.market_id).await {
+                if let Some(price) = snapshot.odds.get(position.option_index as usize) {
+                    prices.push((position.market_id, position.option_index, price_units(*price)));
+                }
+            }
+        }
+        
+        performance(&trades, &positions, |market_id, option_index| {
+            prices.iter()
+                .find(|(m, o, _)| *m == market_id && *o == option_index)
+                .map(|(_, _, price)| *price)
+        })
+    }
+}
+
+/// Schedules bot operations for the next block proposed on this chain.
+struct BotMutationRoot {
+    runtime: Arc<ServiceRuntime<BotService>>,
+}
+
+#[Object]
+impl BotMutationRoot {
+    async fn configure(&self, config: BotConfig) -> bool {
+        self.runtime.schedule_operation(&Operation::Configure { config });
+        true
+    }
+    
+    async fn start(&self) -> bool {
+        self.runtime.schedule_operation(&Operation::Start);
+        true
+    }
+    
+    async fn stop(&self) -> bool {
+        self.runtime.schedule_operation(&Operation::Stop);
+        true
+    }
+    
+    async fn execute(&self) -> bool {
+        self.runtime.schedule_operation(&Operation::Execute);
+        true
+    }
+}
+
+/// Converts a probability to a price scaled by `PRECISION`.
+fn price_units(value: f64) -> u64 {
+    (value * PRECISION as f64) as u64
 }
@@ -0,0 +1,182 @@
+use prediction_market::{BPS_DENOMINATOR, PRECISION};
+use serde::{Deserialize, Serialize};
+
+use crate::{BotPosition, TradeRecord};
+
+/// Performance over the trade history. Amounts are in the market's fixed-point units
+/// (`PRECISION` per token).
+#[derive(Clone, Debug, Default, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct PerformanceMetrics {
+    pub realised_pnl: i64,
+    /// Open positions marked at the last known odds.
+    pub unrealised_pnl: i64,
+    pub closed_trades: u64,
+    pub winning_trades: u64,
+    /// Share of closed trades with positive PnL, in basis points.
+    pub win_rate_bps: u64,
+    /// Largest fall of cumulative realised PnL from a previous peak.
+    pub max_drawdown: i64,
+    /// Mean over standard deviation of per-trade realised PnL, scaled by `PRECISION`.
+    pub sharpe_ratio: i64,
+}
+
+/// Computes the metrics from `trades` in execution order. `price` returns the current
+/// price of an option, scaled by `PRECISION`.
+pub fn performance(
+    trades: &[TradeRecord],
+    positions: &[BotPosition],
+    price: impl Fn(u64, u8) -> Option<u64>,
+) -> PerformanceMetrics {
+    let pnls: Vec<i64> = trades.iter().filter_map(|t| t.pnl).collect();
+    let closed_trades = pnls.len() as u64;
+    let winning_trades = pnls.iter().filter(|pnl| **pnl > 0).count() as u64;
+
+    let mut cumulative = 0i64;
+    let mut peak = 0i64;
+    let mut max_drawdown = 0i64;
+    for pnl in &pnls {
+        cumulative += pnl;
+        peak = peak.max(cumulative);
+        max_drawdown = max_drawdown.max(peak - cumulative);
+    }
+
+    let unrealised_pnl = positions
+        .iter()
+        .filter_map(|p| {
+            let price = price(p.market_id, p.option_index)?;
+            let value = (p.shares as u128 * price as u128 / PRECISION as u128) as i64;
+            Some(value - p.cost as i64)
+        })
+        .sum();
+
+    PerformanceMetrics {
+        realised_pnl: cumulative,
+        unrealised_pnl,
+        closed_trades,
+        winning_trades,
+        win_rate_bps: if closed_trades == 0 {
+            0
+        } else {
+            winning_trades * BPS_DENOMINATOR / closed_trades
+        },
+        max_drawdown,
+        sharpe_ratio: sharpe_ratio(&pnls),
+    }
+}
+
+fn sharpe_ratio(pnls: &[i64]) -> i64 {
+    if pnls.len() < 2 {
+        return 0;
+    }
+    let n = pnls.len() as i128;
+    let mean = pnls.iter().map(|pnl| *pnl as i128).sum::<i128>() / n;
+    let variance = pnls
+        .iter()
+        .map(|pnl| (*pnl as i128 - mean).pow(2))
+        .sum::<i128>()
+        / n;
+    let deviation = isqrt(variance as u128) as i128;
+    if deviation == 0 {
+        return 0;
+    }
+    (mean * PRECISION as i128 / deviation) as i64
+}
+
+fn isqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+    let mut x = value;
+    let mut y = x / 2 + x % 2;
+    while y < x {
+        x = y;
+        y = (x + value / x) / 2;
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use linera_sdk::base::Amount;
+
+    use super::*;
+
+    fn closed(pnl: i64) -> TradeRecord {
+        TradeRecord {
+            market_id: 1,
+            option_index: 0,
+            amount: Amount::ZERO,
+            shares: 0,
+            price: 0.5,
+            is_buy: false,
+            timestamp: 0,
+            pnl: Some(pnl),
+        }
+    }
+
+    #[test]
+    fn drawdown_is_measured_from_the_running_peak() {
+        let trades: Vec<TradeRecord> = [100, -30, -50, 200, -100].map(closed).into();
+        let metrics = performance(&trades, &[], |_, _| None);
+        assert_eq!(metrics.realised_pnl, 120);
+        assert_eq!(metrics.max_drawdown, 100);
+        assert_eq!(metrics.closed_trades, 5);
+        assert_eq!(metrics.winning_trades, 2);
+        assert_eq!(metrics.win_rate_bps, 4_000);
+    }
+
+    #[test]
+    fn drawdown_counts_losses_before_any_gain() {
+        let trades: Vec<TradeRecord> = [-40, -10, 30].map(closed).into();
+        assert_eq!(performance(&trades, &[], |_, _| None).max_drawdown, 50);
+    }
+
+    #[test]
+    fn buys_are_not_closed_trades() {
+        let buy = TradeRecord {
+            is_buy: true,
+            pnl: None,
+            ..closed(0)
+        };
+        let metrics = performance(&[buy], &[], |_, _| None);
+        assert_eq!(metrics.closed_trades, 0);
+        assert_eq!(metrics.win_rate_bps, 0);
+        assert_eq!(metrics.sharpe_ratio, 0);
+    }
+
+    #[test]
+    fn sharpe_ratio_is_mean_over_deviation() {
+        assert_eq!(sharpe_ratio(&[100, -50, 100, -50]), 333_333);
+        assert_eq!(sharpe_ratio(&[-100, 50, -100, 50]), -333_333);
+    }
+
+    #[test]
+    fn sharpe_ratio_needs_spread_and_two_trades() {
+        assert_eq!(sharpe_ratio(&[]), 0);
+        assert_eq!(sharpe_ratio(&[500]), 0);
+        assert_eq!(sharpe_ratio(&[70, 70, 70]), 0);
+    }
+
+    #[test]
+    fn unrealised_pnl_marks_positions_at_current_price() {
+        let position = BotPosition {
+            market_id: 1,
+            option_index: 0,
+            shares: 2 * PRECISION,
+            cost: PRECISION,
+        };
+        let metrics = performance(&[], &[position], |_, _| Some(PRECISION * 3 / 4));
+        assert_eq!(metrics.unrealised_pnl, (PRECISION / 2) as i64);
+    }
+
+    #[test]
+    fn isqrt_rounds_down() {
+        assert_eq!(isqrt(0), 0);
+        assert_eq!(isqrt(1), 1);
+        assert_eq!(isqrt(2), 1);
+        assert_eq!(isqrt(15), 3);
+        assert_eq!(isqrt(16), 4);
+        assert_eq!(isqrt(5_625), 75);
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+    }
+}
@@ -0,0 +1,136 @@
+use linera_sdk::base::Amount;
+use serde::{Deserialize, Serialize};
+
+use crate::{to_units, BotConfig, BotError, BotStats};
+
+const DAY_MICROS: u64 = 86_400_000_000;
+
+/// Limits applied to every order on top of `BotParameters.max_position_size`.
+/// A zero limit is not enforced.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RiskLimits {
+    /// Largest single order.
+    pub max_trade_size: Amount,
+    /// Collateral committed across all options of one market.
+    pub max_market_exposure: Amount,
+    /// Collateral committed across all markets.
+    pub max_total_exposure: Amount,
+    /// Realised loss after which the bot stops for the rest of the day.
+    pub daily_loss_limit: Amount,
+    /// Microseconds to wait between runs that trade the same market. The legs of one run
+    /// are not held back by each other.
+    pub market_cooldown: u64,
+}
+
+/// Running state the limits are checked against.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct RiskState {
+    pub day: u64,
+    /// Realised PnL of the current day, in fixed-point units.
+    pub day_pnl: i64,
+    /// Time of the last run that traded each market.
+    pub last_trades: Vec<(u64, u64)>,
+}
+
+impl RiskState {
+    /// Resets the daily PnL when `now` falls on a new day.
+    pub fn roll_day(&mut self, now: u64) {
+        let day = now / DAY_MICROS;
+        if day != self.day {
+            self.day = day;
+            self.day_pnl = 0;
+        }
+    }
+
+    pub fn record_pnl(&mut self, pnl: i64, now: u64) {
+        self.roll_day(now);
+        self.day_pnl += pnl;
+    }
+
+    pub fn loss_limit_hit(&self, limits: &RiskLimits) -> bool {
+        limits.daily_loss_limit > Amount::ZERO
+            && self.day_pnl <= -(to_units(limits.daily_loss_limit) as i64)
+    }
+
+    pub fn record_trade(&mut self, market_id: u64, now: u64) {
+        self.last_trades.retain(|(m, _)| *m != market_id);
+        self.last_trades.push((market_id, now));
+    }
+
+    fn last_trade(&self, market_id: u64) -> Option<u64> {
+        self.last_trades
+            .iter()
+            .find(|(m, _)| *m == market_id)
+            .map(|(_, at)| *at)
+    }
+}
+
+/// An order the bot is about to send, `price` as a probability.
+pub struct Order {
+    pub market_id: u64,
+    pub option_index: u8,
+    pub amount: Amount,
+    pub price: f64,
+    pub is_buy: bool,
+}
+
+/// Checks `order` against the configured limits and returns the amount that may be traded.
+/// Buys are trimmed to the remaining exposure headroom and blocked once there is none.
+/// Sells only reduce exposure, so the odds band and exposure caps apply to buys alone;
+/// sells are bounded by `max_trade_size` and by the shares held.
+pub fn check_order(
+    order: &Order,
+    config: &BotConfig,
+    max_position_size: Amount,
+    stats: &BotStats,
+    state: &RiskState,
+    now: u64,
+) -> Result<Amount, BotError> {
+    let limits = &config.risk;
+    if state.loss_limit_hit(limits) {
+        return Err(BotError::DailyLossLimit);
+    }
+    if let Some(last) = state.last_trade(order.market_id) {
+        if now < last.saturating_add(limits.market_cooldown) {
+            return Err(BotError::Cooldown);
+        }
+    }
+
+    let mut amount = order.amount;
+    if limits.max_trade_size > Amount::ZERO {
+        amount = amount.min(limits.max_trade_size);
+    }
+    if !order.is_buy {
+        return Ok(amount);
+    }
+
+    // The odds band is only enforced once it is a proper range.
+    if config.max_odds_threshold > config.min_odds_threshold
+        && !(config.min_odds_threshold..=config.max_odds_threshold).contains(&order.price)
+    {
+        return Err(BotError::OddsOutOfRange);
+    }
+
+    let exposure = |filter: &dyn Fn(u64, u8) -> bool| {
+        stats
+            .current_positions
+            .iter()
+            .filter(|p| filter(p.market_id, p.option_index))
+            .fold(Amount::ZERO, |total, p| total.saturating_add(p.size()))
+    };
+    let position = exposure(&|m, o| m == order.market_id && o == order.option_index);
+    amount = amount.min(max_position_size.saturating_sub(position));
+    let caps = [
+        (limits.max_market_exposure, exposure(&|m, _| m == order.market_id)),
+        (limits.max_total_exposure, exposure(&|_, _| true)),
+    ];
+    for (cap, used) in caps {
+        if cap > Amount::ZERO {
+            amount = amount.min(cap.saturating_sub(used));
+        }
+    }
+    if amount == Amount::ZERO {
+        return Err(BotError::PositionSizeExceeded);
+    }
+    Ok(amount)
+}
@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Automatic runs triggered by market updates. Each watched market is analyzed and traded
+/// at most once per interval, and all runs within an interval share `budget` trades.
+/// A zero interval is not used; with both zero the bot only trades on `Execute`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Schedule {
+    pub every_blocks: u64,
+    pub every_micros: u64,
+    /// Trades allowed per interval across all markets. Zero leaves each run capped by
+    /// `max_trades_per_block` only.
+    pub budget: u64,
+}
+
+/// A point in chain time.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize)]
+pub struct Moment {
+    pub height: u64,
+    pub micros: u64,
+}
+
+impl Schedule {
+    pub fn is_enabled(&self) -> bool {
+        self.every_blocks > 0 || self.every_micros > 0
+    }
+
+    /// Whether a whole interval separates `since` from `now`.
+    fn elapsed(&self, since: Moment, now: Moment) -> bool {
+        (self.every_blocks > 0 && now.height >= since.height.saturating_add(self.every_blocks))
+            || (self.every_micros > 0
+                && now.micros >= since.micros.saturating_add(self.every_micros))
+    }
+}
+
+/// Progress of the schedule: the current budget interval and the last run on each market.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ScheduleState {
+    pub interval_start: Moment,
+    pub trades_in_interval: u64,
+    pub last_runs: Vec<(u64, Moment)>,
+}
+
+impl ScheduleState {
+    /// Number of trades a run on `market_id` may make at `now`; zero when the market
+    /// already ran this interval or the budget is spent. Opens a new budget interval
+    /// once the current one has elapsed.
+    pub fn allowance(&mut self, schedule: &Schedule, market_id: u64, now: Moment) -> u64 {
+        if let Some(last) = self.last_run(market_id) {
+            if !schedule.elapsed(last, now) {
+                return 0;
+            }
+        }
+        if schedule.elapsed(self.interval_start, now) {
+            self.interval_start = now;
+            self.trades_in_interval = 0;
+        }
+        if schedule.budget == 0 {
+            return u64::MAX;
+        }
+        schedule.budget.saturating_sub(self.trades_in_interval)
+    }
+
+    pub fn record_run(&mut self, market_id: u64, now: Moment, trades: u64) {
+        self.last_runs.retain(|(m, _)| *m != market_id);
+        self.last_runs.push((market_id, now));
+        self.trades_in_interval += trades;
+    }
+
+    fn last_run(&self, market_id: u64) -> Option<Moment> {
+        self.last_runs
+            .iter()
+            .find(|(m, _)| *m == market_id)
+            .map(|(_, at)| *at)
+    }
+}
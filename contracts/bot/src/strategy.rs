@@ -0,0 +1,251 @@
+use linera_sdk::base::Amount;
+use serde::{Deserialize, Serialize};
+
+use crate::{from_units, to_units, BotConfig, MarketSnapshot, Signal, TradeAction};
+
+/// What a strategy sees when it analyzes a market.
+pub struct StrategyContext<'a> {
+    pub market: &'a MarketSnapshot,
+    /// Collateral committed to each option of this market.
+    pub positions: &'a [(u8, Amount)],
+    pub config: &'a BotConfig,
+}
+
+impl StrategyContext<'_> {
+    pub fn position(&self, option_index: usize) -> Amount {
+        self.positions
+            .iter()
+            .find(|(o, _)| *o as usize == option_index)
+            .map_or(Amount::ZERO, |(_, size)| *size)
+    }
+
+    /// Change of an option's price since the oldest recorded trade.
+    fn trend(&self, option_index: usize) -> f64 {
+        self.market
+            .price_history
+            .first()
+            .and_then(|start| start.get(option_index))
+            .map_or(0.0, |start| self.market.odds[option_index] - start)
+    }
+
+    fn leader(&self) -> (usize, f64) {
+        self.market
+            .odds
+            .iter()
+            .cloned()
+            .enumerate()
+            .fold((0, 0.0f64), |best, (i, p)| if p > best.1 { (i, p) } else { best })
+    }
+
+    fn laggard(&self) -> (usize, f64) {
+        self.market
+            .odds
+            .iter()
+            .cloned()
+            .enumerate()
+            .fold((0, 1.0f64), |best, (i, p)| if p < best.1 { (i, p) } else { best })
+    }
+}
+
+/// Turns a market snapshot into the legs to trade; an empty list means hold.
+pub trait TradingStrategy {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal>;
+}
+
+fn leg(option_index: usize, action: TradeAction, confidence: f64) -> Signal {
+    Signal {
+        option_index: option_index as u8,
+        action,
+        confidence: confidence.min(1.0),
+        size: None,
+    }
+}
+
+/// Buys the favourite while its price keeps rising.
+pub struct Momentum;
+
+impl TradingStrategy for Momentum {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let (leader, max_odds) = context.leader();
+        if max_odds > 0.6 && context.trend(leader) >= 0.0 {
+            vec![leg(leader, TradeAction::Buy, max_odds)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Buys the outsider while its price keeps falling.
+pub struct Contrarian;
+
+impl TradingStrategy for Contrarian {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let (laggard, min_odds) = context.laggard();
+        if min_odds < 0.3 && context.trend(laggard) <= 0.0 {
+            vec![leg(laggard, TradeAction::Buy, 1.0 - min_odds)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+/// Trades prices that do not sum to one back across every outcome.
+///
+/// The prediction market renormalises its odds after every trade, so this only fires on
+/// odds fed from elsewhere, such as a backtest series; against a live market it holds.
+pub struct Arbitrage;
+
+impl TradingStrategy for Arbitrage {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let odds = &context.market.odds;
+        let total: f64 = odds.iter().sum();
+        let (action, confidence) = if total < 0.95 {
+            (TradeAction::Buy, 0.95 - total + 0.5)
+        } else if total > 1.05 {
+            (TradeAction::Sell, total - 1.05 + 0.5)
+        } else {
+            return Vec::new();
+        };
+        (0..odds.len())
+            .map(|index| leg(index, action, confidence))
+            .collect()
+    }
+}
+
+/// Buys the cheapest outcome and, when it holds shares of the richest, sells those too.
+pub struct MarketMaker;
+
+impl TradingStrategy for MarketMaker {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let (leader, max_odds) = context.leader();
+        let (laggard, min_odds) = context.laggard();
+        let spread = max_odds - min_odds;
+        if spread <= 0.2 || leader == laggard {
+            return Vec::new();
+        }
+        let mut legs = vec![leg(laggard, TradeAction::Buy, 0.5 + spread)];
+        if context.position(leader) > Amount::ZERO {
+            legs.push(leg(leader, TradeAction::Sell, 0.5 + spread));
+        }
+        legs
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct MeanReversionParams {
+    /// Number of recent price points averaged.
+    pub lookback: usize,
+    /// Distance from the average, in probability, that triggers a trade.
+    pub threshold: f64,
+}
+
+/// Buys options trading below their recent average and sells held ones trading above it.
+pub struct MeanReversion(pub MeanReversionParams);
+
+impl TradingStrategy for MeanReversion {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let history = &context.market.price_history;
+        let window = &history[history.len().saturating_sub(self.0.lookback)..];
+        if window.is_empty() {
+            return Vec::new();
+        }
+
+        let mut signals = Vec::new();
+        for (index, price) in context.market.odds.iter().enumerate() {
+            let prices: Vec<f64> = window.iter().filter_map(|p| p.get(index).copied()).collect();
+            if prices.is_empty() {
+                continue;
+            }
+            let mean = prices.iter().sum::<f64>() / prices.len() as f64;
+            let deviation = price - mean;
+            if deviation < -self.0.threshold {
+                signals.push(leg(index, TradeAction::Buy, 0.5 - deviation));
+            } else if deviation > self.0.threshold && context.position(index) > Amount::ZERO {
+                signals.push(leg(index, TradeAction::Sell, 0.5 + deviation));
+            }
+        }
+        signals
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct ValueBettingParams {
+    /// Fair probability of each option, from an external model.
+    pub fair_probabilities: Vec<f64>,
+    /// Minimum edge over the market price before betting.
+    pub min_edge: f64,
+    /// Fraction of the full Kelly stake to bet.
+    pub kelly_fraction: f64,
+    pub bankroll: Amount,
+}
+
+/// Buys options priced below their fair probability, sized by the Kelly criterion.
+pub struct ValueBetting(pub ValueBettingParams);
+
+impl TradingStrategy for ValueBetting {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let mut signals = Vec::new();
+        for (index, (price, fair)) in context
+            .market
+            .odds
+            .iter()
+            .zip(&self.0.fair_probabilities)
+            .enumerate()
+        {
+            let edge = fair - price;
+            if edge <= self.0.min_edge || *price >= 1.0 {
+                continue;
+            }
+            // Full Kelly stake for a share paying 1 bought at `price`.
+            let kelly = edge / (1.0 - price);
+            let stake = to_units(self.0.bankroll) as f64 * kelly * self.0.kelly_fraction;
+            signals.push(Signal {
+                size: Some(from_units(stake as u64)),
+                ..leg(index, TradeAction::Buy, 0.5 + kelly)
+            });
+        }
+        signals
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct GridParams {
+    /// Distance between grid levels, in probability.
+    pub spacing: f64,
+    /// Number of levels on each side of the reference price.
+    pub levels: u8,
+}
+
+/// Buys each level the price falls below its recent average and sells held shares
+/// each level it rises above it.
+pub struct GridMarketMaker(pub GridParams);
+
+impl TradingStrategy for GridMarketMaker {
+    fn signals(&self, context: &StrategyContext) -> Vec<Signal> {
+        let history = &context.market.price_history;
+        if history.is_empty() || self.0.spacing <= 0.0 {
+            return Vec::new();
+        }
+
+        let mut signals = Vec::new();
+        for (index, price) in context.market.odds.iter().enumerate() {
+            let prices: Vec<f64> = history.iter().filter_map(|p| p.get(index).copied()).collect();
+            if prices.is_empty() {
+                continue;
+            }
+            let reference = prices.iter().sum::<f64>() / prices.len() as f64;
+            let steps = ((price - reference) / self.0.spacing).trunc();
+            let depth = steps.abs().min(self.0.levels as f64);
+            if depth < 1.0 {
+                continue;
+            }
+            let confidence = 0.5 + depth / (2.0 * self.0.levels as f64);
+            if steps < 0.0 {
+                signals.push(leg(index, TradeAction::Buy, confidence));
+            } else if context.position(index) > Amount::ZERO {
+                signals.push(leg(index, TradeAction::Sell, confidence));
+            }
+        }
+        signals
+    }
+}
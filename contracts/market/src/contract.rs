@@ -3,13 +3,16 @@
 mod state;
 
 use linera_sdk::{
-    base::WithContractAbi,
+    base::{ChainId, Owner, StreamName, WithContractAbi},
     views::{RootView, View},
     Contract, ContractRuntime,
 };
 use prediction_market::{
-    InstantiationArgument, MarketResponse, MarketState, MarketStatus, Message, Operation,
-    PredictionMarketAbi, update_odds_after_trade, PRECISION,
+    BPS_DENOMINATOR, InstantiationArgument, MarketData, MarketResponse, MarketState, MarketStatus,
+    MarketEvent, MarketType, MarketUpdate, Message, Operation, OracleLink, Position, PredictionMarketAbi,
+    PricePoint, ResolutionQuery, ScalarRange, cost_of_shares, fee_for_amount, lp_pool_value, lp_shares_for_deposit, payout_per_share,
+    refund_for_position, shares_for_amount, update_odds_after_trade, MARKET_UPDATES_STREAM, PRECISION,
+    PRICE_HISTORY_LEN, RESOLUTION_REQUESTS_STREAM,
 };
 use state::ApplicationState;
 
@@ -28,6 +31,7 @@ impl Contract for PredictionMarketContract {
     type Message = Message;
     type Parameters = ();
     type InstantiationArgument = InstantiationArgument;
+    type EventValue = MarketEvent;
 
     async fn load(runtime: ContractRuntime<Self>) -> Self {
         let state = ApplicationState::load(runtime.root_view_storage_context())
@@ -37,8 +41,20 @@ impl Contract for PredictionMarketContract {
     }
 
     async fn instantiate(&mut self, argument: Self::InstantiationArgument) {
-        self.state.admin.set(argument.admin);
+        self.state.admin.set(argument.admin.clone());
         self.state.oracle_threshold.set(argument.oracle_threshold);
+        for (oracle, weight) in argument.oracle_weights {
+            self.state.oracle_weights.insert(&oracle, weight).unwrap();
+        }
+        let mut fee_config = argument.fee_config;
+        if let Err(error) = fee_config.validate() {
+            panic!("{}", error);
+        }
+        if fee_config.treasury.is_none() {
+            fee_config.treasury = argument.admin.clone();
+        }
+        self.state.fee_config.set(fee_config);
+        self.state.dispute_config.set(argument.dispute_config);
         self.state.market_counter.set(0);
     }
 
@@ -51,34 +67,148 @@ impl Contract for PredictionMarketContract {
                 options,
                 initial_liquidity,
                 resolution_time,
+                parent_market_id,
+                parent_outcome,
             } => {
-                let market_id = self.state.market_counter.get() + 1;
-                self.state.market_counter.set(market_id);
+                match (parent_market_id, parent_outcome) {
+                    (None, None) => {}
+                    (Some(parent_id), Some(parent_outcome)) => {
+                        let Ok(Some(parent)) = self.state.markets.get(&parent_id).await else {
+                            return MarketResponse::error("Parent market not found");
+                        };
+                        if parent.market_type != MarketType::Categorical
+                            || parent_outcome as usize >= parent.options.len()
+                        {
+                            return MarketResponse::error("Invalid parent outcome");
+                        }
+                        if parent.status != MarketStatus::Active {
+                            return MarketResponse::error("Parent market is not active");
+                        }
+                    }
+                    _ => {
+                        return MarketResponse::error(
+                            "Parent market and parent outcome must be given together",
+                        );
+                    }
+                }
+
+                let market_id = self.next_market_id();
+                let creator = self.signer();
 
                 let num_options = options.len() as u64;
                 let initial_odds = vec![PRECISION / num_options; options.len()];
 
-                let market = MarketState {
+                let mut market = MarketState {
                     market_id,
                     title,
                     description,
                     category,
+                    market_type: MarketType::Categorical,
                     options,
+                    scalar_range: None,
                     odds: initial_odds,
                     total_volume: 0,
                     liquidity: initial_liquidity,
                     status: MarketStatus::Active,
                     resolved_outcome: None,
+                    resolved_value: None,
+                    proposed_outcome: None,
+                    proposed_value: None,
+                    dispute_deadline: None,
+                    disputer: None,
+                    disputed_outcome: None,
+                    disputed_value: None,
+                    dispute_bond: 0,
                     created_at: self.runtime.system_time().micros() as u64,
                     resolution_time,
                     trade_count: 0,
+                    creator: creator.clone(),
+                    fee_bps: None,
+                    lp_fees: 0,
+                    complete_sets: 0,
+                    collateral: initial_liquidity,
+                    outstanding_shares: vec![0; num_options as usize],
+                    outstanding_cost: vec![0; num_options as usize],
+                    total_lp_shares: initial_liquidity,
+                    parent_market_id,
+                    parent_outcome,
+                    oracle_pending: false,
                 };
+                market.oracle_pending = self.request_resolution(&market);
 
                 self.state.markets.insert(&market_id, market).unwrap();
+                self.credit_lp_shares(market_id, &creator, initial_liquidity).await;
 
                 MarketResponse::success("Market created successfully").with_market_id(market_id)
             }
 
+            Operation::CreateScalarMarket {
+                title,
+                description,
+                category,
+                lower_bound,
+                upper_bound,
+                unit,
+                initial_liquidity,
+                resolution_time,
+            } => {
+                let scalar_range = ScalarRange {
+                    lower_bound,
+                    upper_bound,
+                    unit,
+                };
+                if let Err(error) = scalar_range.validate() {
+                    return MarketResponse::error(error);
+                }
+
+                let market_id = self.next_market_id();
+                let creator = self.signer();
+
+                let mut market = MarketState {
+                    market_id,
+                    title,
+                    description,
+                    category,
+                    market_type: MarketType::Scalar,
+                    options: vec!["Long".to_string(), "Short".to_string()],
+                    scalar_range: Some(scalar_range),
+                    odds: vec![PRECISION / 2; 2],
+                    total_volume: 0,
+                    liquidity: initial_liquidity,
+                    status: MarketStatus::Active,
+                    resolved_outcome: None,
+                    resolved_value: None,
+                    proposed_outcome: None,
+                    proposed_value: None,
+                    dispute_deadline: None,
+                    disputer: None,
+                    disputed_outcome: None,
+                    disputed_value: None,
+                    dispute_bond: 0,
+                    created_at: self.runtime.system_time().micros() as u64,
+                    resolution_time,
+                    trade_count: 0,
+                    creator: creator.clone(),
+                    fee_bps: None,
+                    lp_fees: 0,
+                    complete_sets: 0,
+                    collateral: initial_liquidity,
+                    outstanding_shares: vec![0; 2],
+                    outstanding_cost: vec![0; 2],
+                    total_lp_shares: initial_liquidity,
+                    parent_market_id: None,
+                    parent_outcome: None,
+                    oracle_pending: false,
+                };
+                market.oracle_pending = self.request_resolution(&market);
+
+                self.state.markets.insert(&market_id, market).unwrap();
+                self.credit_lp_shares(market_id, &creator, initial_liquidity).await;
+
+                MarketResponse::success("Scalar market created successfully")
+                    .with_market_id(market_id)
+            }
+
             Operation::PlaceTrade {
                 market_id,
                 option_index,
@@ -96,8 +226,57 @@ impl Contract for PredictionMarketContract {
                         if option_index as usize >= market.options.len() {
                             return MarketResponse::error("Invalid option index");
                         }
+                        // Once the parent settles elsewhere the market can only be refunded.
+                        if self.parent_condition(&market).await == Some(false) {
+                            return MarketResponse::error("Parent condition not met");
+                        }
 
                         let price = market.odds[option_index as usize];
+                        let fee_config = self.state.fee_config.get().clone();
+                        let fee_bps = market.fee_bps.unwrap_or(fee_config.trading_fee_bps);
+                        let fee = fee_for_amount(amount, fee_bps);
+                        let shares = if is_buy {
+                            shares_for_amount(amount - fee, price)
+                        } else {
+                            shares_for_amount(amount, price)
+                        };
+
+                        let trader = self.signer();
+                        let mut position = self.position(market_id, &trader, option_index).await;
+
+                        let cost = &mut market.outstanding_cost[option_index as usize];
+                        if is_buy {
+                            position.credit(shares, price);
+                            *cost += cost_of_shares(shares, price);
+                        } else {
+                            if position.shares < shares {
+                                return MarketResponse::error("Insufficient shares to sell");
+                            }
+                            position.shares -= shares;
+                            *cost = cost.saturating_sub(refund_for_sold(shares, &position));
+                        }
+                        self.save_position(market_id, position);
+
+                        let (protocol_fee, mut creator_fee, mut lp_fee) = fee_config.split(fee);
+                        match &fee_config.treasury {
+                            Some(treasury) => self.credit_fees(treasury, protocol_fee).await,
+                            None => lp_fee += protocol_fee,
+                        }
+                        if creator_fee > 0 && market.creator == "anonymous" {
+                            lp_fee += creator_fee;
+                            creator_fee = 0;
+                        }
+                        self.credit_fees(&market.creator, creator_fee).await;
+                        market.lp_fees += lp_fee;
+
+                        let outstanding = &mut market.outstanding_shares[option_index as usize];
+                        if is_buy {
+                            *outstanding += shares;
+                            market.collateral += amount - (fee - lp_fee);
+                        } else {
+                            *outstanding = outstanding.saturating_sub(shares);
+                            market.collateral = market.collateral.saturating_sub(amount - lp_fee);
+                        }
                         
                         market.total_volume += amount;
                         market.trade_count += 1;
@@ -110,13 +289,9 @@ impl Contract for PredictionMarketContract {
                         );
 
                         let trade_id = market.trade_count;
+                        self.record_price(market_id, market.odds.clone()).await;
                         self.state.markets.insert(&market_id, market).unwrap();
 
-                        let trader = self.runtime
-                            .authenticated_signer()
-                            .map(|o| o.to_string())
-                            .unwrap_or_else(|| "anonymous".to_string());
-
                         self.runtime
                             .prepare_message(Message::TradeNotification {
                                 market_id,
@@ -127,17 +302,28 @@ impl Contract for PredictionMarketContract {
                             })
                             .send_to(self.runtime.chain_id());
 
-                        MarketResponse::success(format!(
-                            "Trade executed at price {}",
-                            price as f64 / PRECISION as f64
+                        let response = MarketResponse::success(format!(
+                            "Trade executed at price {} with fee {}",
+                            price as f64 / PRECISION as f64,
+                            fee
                         ))
                         .with_trade_id(trade_id)
+                        .with_fill(price, shares);
+                        if is_buy {
+                            response
+                        } else {
+                            response.with_payout(amount - fee)
+                        }
                     }
                     _ => MarketResponse::error("Market not found"),
                 }
             }
 
-            Operation::ResolveMarket { market_id, outcome } => {
+            Operation::ResolveMarket {
+                market_id,
+                outcome,
+                value,
+            } => {
                 let market = self.state.markets.get(&market_id).await;
                 
                 match market {
@@ -145,19 +331,174 @@ impl Contract for PredictionMarketContract {
                         if market.status != MarketStatus::Active {
                             return MarketResponse::error("Market already resolved");
                         }
+                        if market.oracle_pending {
+                            return MarketResponse::error("Market is awaiting the oracle network");
+                        }
+
+                        match self.parent_condition(&market).await {
+                            Some(true) => {}
+                            Some(false) => {
+                                market.status = MarketStatus::Cancelled;
+                                self.state.markets.insert(&market_id, market).unwrap();
+
+                                self.runtime
+                                    .prepare_message(Message::MarketCancelled { market_id })
+                                    .send_to(self.runtime.chain_id());
+
+                                return MarketResponse::success(
+                                    "Parent condition not met, market cancelled for refunds",
+                                );
+                            }
+                            None => {
+                                return MarketResponse::error("Parent market not resolved yet");
+                            }
+                        }
+
+                        let (outcome, value) = match market.resolution(outcome, value) {
+                            Ok(resolution) => resolution,
+                            Err(error) => return MarketResponse::error(error),
+                        };
+
+                        self.propose_resolution(market, outcome, value);
+
+                        MarketResponse::success("Resolution proposed")
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::DisputeResolution {
+                market_id,
+                outcome,
+                value,
+                bond,
+            } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        let now = self.runtime.system_time().micros() as u64;
+                        let window_open = market.status == MarketStatus::Proposed
+                            && market.dispute_deadline.is_some_and(|deadline| now < deadline);
+                        if !window_open {
+                            return MarketResponse::error("No resolution open to dispute");
+                        }
+
+                        let (outcome, value) = match market.resolution(outcome, value) {
+                            Ok(resolution) => resolution,
+                            Err(error) => return MarketResponse::error(error),
+                        };
+                        if (outcome, value) == (market.proposed_outcome, market.proposed_value) {
+                            return MarketResponse::error("Dispute must propose a different resolution");
+                        }
 
-                        if outcome as usize >= market.options.len() {
-                            return MarketResponse::error("Invalid outcome");
+                        let disputer = self.signer();
+                        let mut is_holder = false;
+                        for option_index in 0..market.options.len() as u8 {
+                            if self.position(market_id, &disputer, option_index).await.shares > 0 {
+                                is_holder = true;
+                                break;
+                            }
+                        }
+                        if !is_holder {
+                            return MarketResponse::error("Only share holders can dispute");
+                        }
+
+                        if bond < self.state.dispute_config.get().dispute_bond {
+                            return MarketResponse::error("Bond below the required dispute bond");
                         }
 
-                        market.status = MarketStatus::Resolved;
-                        market.resolved_outcome = Some(outcome);
+                        market.status = MarketStatus::Disputed;
+                        market.disputer = Some(disputer.clone());
+                        market.disputed_outcome = outcome;
+                        market.disputed_value = value;
+                        market.dispute_bond = bond;
                         self.state.markets.insert(&market_id, market).unwrap();
 
                         self.runtime
-                            .prepare_message(Message::MarketResolved { market_id, outcome })
+                            .prepare_message(Message::ResolutionDisputed {
+                                market_id,
+                                disputer,
+                            })
                             .send_to(self.runtime.chain_id());
 
+                        MarketResponse::success("Resolution disputed, escalated to arbitration")
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::ArbitrateDispute {
+                market_id,
+                outcome,
+                value,
+            } => {
+                let signer = self.signer();
+                if self.state.admin.get().as_deref() != Some(signer.as_str()) {
+                    return MarketResponse::error("Only the admin can arbitrate disputes");
+                }
+
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if market.status != MarketStatus::Disputed {
+                            return MarketResponse::error("Market is not disputed");
+                        }
+
+                        let (outcome, value) = match market.resolution(outcome, value) {
+                            Ok(resolution) => resolution,
+                            Err(error) => return MarketResponse::error(error),
+                        };
+
+                        let bond = market.dispute_bond;
+                        let challenge_won =
+                            (outcome, value) != (market.proposed_outcome, market.proposed_value);
+                        let treasury = self.state.fee_config.get().treasury.clone();
+                        if let Some(disputer) = market.disputer.clone() {
+                            if challenge_won {
+                                let mut reward = self.state.dispute_config.get().dispute_reward;
+                                if let Some(treasury) = &treasury {
+                                    reward = self.debit_fees(treasury, reward).await;
+                                } else {
+                                    reward = 0;
+                                }
+                                self.credit_fees(&disputer, bond + reward).await;
+                            } else {
+                                match &treasury {
+                                    Some(treasury) => self.credit_fees(treasury, bond).await,
+                                    None => market.collateral += bond,
+                                }
+                            }
+                        }
+
+                        self.finalize_resolution(&mut market, outcome, value);
+
+                        if challenge_won {
+                            MarketResponse::success("Dispute upheld, market resolved")
+                        } else {
+                            MarketResponse::success("Dispute rejected, bond forfeited")
+                        }
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::FinalizeResolution { market_id } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if market.status != MarketStatus::Proposed {
+                            return MarketResponse::error("No undisputed resolution to finalize");
+                        }
+                        if !self.dispute_window_closed(&market) {
+                            return MarketResponse::error("Dispute window still open");
+                        }
+
+                        let (outcome, value) = (market.proposed_outcome, market.proposed_value);
+                        self.finalize_resolution(&mut market, outcome, value);
+
                         MarketResponse::success("Market resolved")
                     }
                     _ => MarketResponse::error("Market not found"),
@@ -168,64 +509,539 @@ impl Contract for PredictionMarketContract {
                 let market = self.state.markets.get(&market_id).await;
                 
                 match market {
-                    Ok(Some(market)) => {
-                        if market.status != MarketStatus::Resolved {
-                            return MarketResponse::error("Market not resolved yet");
+                    Ok(Some(mut market)) => {
+                        if market.status == MarketStatus::Proposed
+                            && self.dispute_window_closed(&market)
+                        {
+                            let (outcome, value) = (market.proposed_outcome, market.proposed_value);
+                            self.finalize_resolution(&mut market, outcome, value);
+                        }
+
+                        let refund = match market.status {
+                            MarketStatus::Active => {
+                                return MarketResponse::error("Market not resolved yet");
+                            }
+                            MarketStatus::Proposed | MarketStatus::Disputed => {
+                                return MarketResponse::error("Resolution is still disputable");
+                            }
+                            MarketStatus::Cancelled => true,
+                            MarketStatus::Resolved => match self.parent_condition(&market).await {
+                                Some(condition_met) => !condition_met,
+                                None => {
+                                    return MarketResponse::error("Parent market not resolved yet");
+                                }
+                            },
+                        };
+
+                        let owner = self.signer();
+                        let mut payout = 0u64;
+                        let mut claimed = Vec::new();
+                        for option_index in 0..market.options.len() as u8 {
+                            let key = (market_id, owner.clone(), option_index);
+                            if let Ok(Some(position)) = self.state.positions.get(&key).await {
+                                payout += if refund {
+                                    refund_for_position(&position)
+                                } else {
+                                    let per_share = payout_per_share(&market, option_index);
+                                    ((position.shares as u128 * per_share as u128)
+                                        / PRECISION as u128) as u64
+                                };
+                                claimed.push((key, position));
+                            }
+                        }
+                        if payout > market.collateral {
+                            return MarketResponse::error(
+                                "Market collateral does not cover the payout",
+                            );
+                        }
+
+                        for (key, position) in claimed {
+                            let index = position.option_index as usize;
+                            let outstanding = &mut market.outstanding_shares[index];
+                            *outstanding = outstanding.saturating_sub(position.shares);
+                            let cost = &mut market.outstanding_cost[index];
+                            *cost = cost.saturating_sub(refund_for_position(&position));
+                            self.state.positions.remove(&key).unwrap();
+                        }
+                        market.collateral -= payout;
+                        self.state.markets.insert(&market_id, market).unwrap();
+
+                        if refund {
+                            MarketResponse::success(format!("Refund claimed: {}", payout))
+                                .with_payout(payout)
+                        } else {
+                            MarketResponse::success(format!("Payout claimed: {}", payout))
+                                .with_payout(payout)
+                        }
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::MintCompleteSet { market_id, amount } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if market.status != MarketStatus::Active {
+                            return MarketResponse::error("Market is not active");
+                        }
+                        if amount == 0 {
+                            return MarketResponse::error("Amount must be positive");
+                        }
+
+                        let owner = self.signer();
+                        let num_options = market.options.len() as u64;
+                        for option_index in 0..market.options.len() as u8 {
+                            let mut position = self.position(market_id, &owner, option_index).await;
+                            position.credit(amount, PRECISION / num_options);
+                            self.save_position(market_id, position);
+                            market.outstanding_cost[option_index as usize] +=
+                                cost_of_shares(amount, PRECISION / num_options);
+                        }
+
+                        market.complete_sets += amount;
+                        market.collateral += amount;
+                        for outstanding in &mut market.outstanding_shares {
+                            *outstanding += amount;
+                        }
+                        self.state.markets.insert(&market_id, market).unwrap();
+
+                        MarketResponse::success(format!("Minted {} complete sets", amount))
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::RedeemCompleteSet { market_id, amount } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if amount == 0 {
+                            return MarketResponse::error("Amount must be positive");
+                        }
+                        if market.collateral < amount {
+                            return MarketResponse::error("Not enough collateral to redeem");
+                        }
+
+                        let owner = self.signer();
+                        let mut positions = Vec::with_capacity(market.options.len());
+                        for option_index in 0..market.options.len() as u8 {
+                            let position = self.position(market_id, &owner, option_index).await;
+                            if position.shares < amount {
+                                return MarketResponse::error(
+                                    "Insufficient shares to redeem complete sets",
+                                );
+                            }
+                            positions.push(position);
+                        }
+
+                        for mut position in positions {
+                            position.shares -= amount;
+                            let cost = &mut market.outstanding_cost[position.option_index as usize];
+                            *cost = cost.saturating_sub(refund_for_sold(amount, &position));
+                            self.save_position(market_id, position);
+                        }
+
+                        market.complete_sets = market.complete_sets.saturating_sub(amount);
+                        market.collateral -= amount;
+                        for outstanding in &mut market.outstanding_shares {
+                            *outstanding = outstanding.saturating_sub(amount);
+                        }
+                        self.state.markets.insert(&market_id, market).unwrap();
+
+                        MarketResponse::success(format!(
+                            "Redeemed {} complete sets for {} collateral",
+                            amount, amount
+                        ))
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::TransferShares {
+                market_chain,
+                market_id,
+                option_index,
+                amount,
+                to,
+            } => {
+                let market_chain = market_chain.unwrap_or(self.runtime.chain_id());
+                if market_chain == self.runtime.chain_id() {
+                    match self.state.markets.get(&market_id).await {
+                        Ok(Some(market)) => {
+                            if option_index as usize >= market.options.len() {
+                                return MarketResponse::error("Invalid option index");
+                            }
+                        }
+                        _ => return MarketResponse::error("Market not found"),
+                    }
+                }
+                if amount == 0 {
+                    return MarketResponse::error("Amount must be positive");
+                }
+
+                let sender = self.signer();
+                let mut position = self
+                    .holding(market_chain, market_id, &sender, option_index)
+                    .await;
+                if position.shares < amount {
+                    return MarketResponse::error("Insufficient shares to transfer");
+                }
+                let avg_price = position.avg_price;
+                position.shares -= amount;
+                self.save_holding(market_chain, market_id, position);
+
+                if to.chain_id == self.runtime.chain_id() {
+                    let recipient = owner_key(to.owner);
+                    let mut received = self
+                        .holding(market_chain, market_id, &recipient, option_index)
+                        .await;
+                    received.credit(amount, avg_price);
+                    self.save_holding(market_chain, market_id, received);
+                } else {
+                    self.runtime
+                        .prepare_message(Message::SharesTransferred {
+                            market_chain,
+                            market_id,
+                            option_index,
+                            shares: amount,
+                            avg_price,
+                            sender,
+                            recipient: to.owner,
+                        })
+                        .with_tracking()
+                        .send_to(to.chain_id);
+                }
+
+                MarketResponse::success(format!("Transferred {} shares", amount))
+            }
+
+            Operation::SetMarketFee { market_id, fee_bps } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if market.creator == "anonymous" || market.creator != self.signer() {
+                            return MarketResponse::error("Only the market creator can set fees");
+                        }
+                        if fee_bps > BPS_DENOMINATOR
+                            || fee_bps > self.state.fee_config.get().max_market_fee_bps
+                        {
+                            return MarketResponse::error("Fee exceeds the maximum market fee");
                         }
-                        MarketResponse::success("Payout claimed")
+
+                        market.fee_bps = Some(fee_bps);
+                        self.state.markets.insert(&market_id, market).unwrap();
+
+                        MarketResponse::success(format!("Market fee set to {} bps", fee_bps))
                     }
                     _ => MarketResponse::error("Market not found"),
                 }
             }
 
+            Operation::WithdrawFees => {
+                let owner = self.signer();
+                let balance = self
+                    .state
+                    .fee_balances
+                    .get(&owner)
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+                if balance == 0 {
+                    return MarketResponse::error("No fees to withdraw");
+                }
+
+                self.state.fee_balances.remove(&owner).unwrap();
+
+                MarketResponse::success(format!("Withdrew {} in fees", balance))
+                    .with_payout(balance)
+            }
+
             Operation::AddLiquidity { market_id, amount } => {
                 let market = self.state.markets.get(&market_id).await;
                 
                 match market {
                     Ok(Some(mut market)) => {
+                        if market.status != MarketStatus::Active {
+                            return MarketResponse::error("Market is not active");
+                        }
+                        if amount == 0 {
+                            return MarketResponse::error("Amount must be positive");
+                        }
+
+                        let Some(shares) = lp_shares_for_deposit(
+                            amount,
+                            lp_pool_value(&market),
+                            market.total_lp_shares,
+                        ) else {
+                            return MarketResponse::error("Pool is drained");
+                        };
                         market.liquidity += amount;
+                        market.collateral += amount;
+                        market.total_lp_shares += shares;
+                        self.state.markets.insert(&market_id, market).unwrap();
+
+                        let provider = self.signer();
+                        self.credit_lp_shares(market_id, &provider, shares).await;
+
+                        MarketResponse::success(format!("Liquidity added for {} LP shares", shares))
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::AddLiquidityFromSets { market_id, sets } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        if market.status != MarketStatus::Active {
+                            return MarketResponse::error("Market is not active");
+                        }
+                        if sets == 0 {
+                            return MarketResponse::error("Amount must be positive");
+                        }
+
+                        let provider = self.signer();
+                        let mut positions = Vec::with_capacity(market.options.len());
+                        for option_index in 0..market.options.len() as u8 {
+                            let position = self.position(market_id, &provider, option_index).await;
+                            if position.shares < sets {
+                                return MarketResponse::error("Insufficient shares to deposit");
+                            }
+                            positions.push(position);
+                        }
+
+                        // The collateral backing the sets stays in the market and now belongs
+                        // to the pool instead of the depositor.
+                        let Some(shares) = lp_shares_for_deposit(
+                            sets,
+                            lp_pool_value(&market),
+                            market.total_lp_shares,
+                        ) else {
+                            return MarketResponse::error("Pool is drained");
+                        };
+                        for mut position in positions {
+                            position.shares -= sets;
+                            let cost = &mut market.outstanding_cost[position.option_index as usize];
+                            *cost = cost.saturating_sub(refund_for_sold(sets, &position));
+                            self.save_position(market_id, position);
+                        }
+                        for outstanding in &mut market.outstanding_shares {
+                            *outstanding = outstanding.saturating_sub(sets);
+                        }
+                        market.complete_sets = market.complete_sets.saturating_sub(sets);
+                        market.liquidity += sets;
+                        market.total_lp_shares += shares;
+                        self.state.markets.insert(&market_id, market).unwrap();
+                        self.credit_lp_shares(market_id, &provider, shares).await;
+
+                        MarketResponse::success(format!(
+                            "Deposited {} complete sets for {} LP shares",
+                            sets, shares
+                        ))
+                    }
+                    _ => MarketResponse::error("Market not found"),
+                }
+            }
+
+            Operation::RemoveLiquidity { market_id, shares } => {
+                let market = self.state.markets.get(&market_id).await;
+
+                match market {
+                    Ok(Some(mut market)) => {
+                        let provider = self.signer();
+                        let key = (market_id, provider);
+                        let balance = self
+                            .state
+                            .lp_shares
+                            .get(&key)
+                            .await
+                            .ok()
+                            .flatten()
+                            .unwrap_or_default();
+                        if shares == 0 || balance < shares || market.total_lp_shares < shares {
+                            return MarketResponse::error("Insufficient LP shares");
+                        }
+
+                        let payout = ((lp_pool_value(&market) as u128 * shares as u128)
+                            / market.total_lp_shares as u128) as u64;
+                        let principal = ((market.liquidity as u128 * shares as u128)
+                            / market.total_lp_shares as u128) as u64;
+                        market.liquidity -= principal;
+                        market.collateral = market.collateral.saturating_sub(payout);
+                        market.total_lp_shares -= shares;
                         self.state.markets.insert(&market_id, market).unwrap();
-                        MarketResponse::success("Liquidity added")
+
+                        if balance == shares {
+                            self.state.lp_shares.remove(&key).unwrap();
+                        } else {
+                            self.state.lp_shares.insert(&key, balance - shares).unwrap();
+                        }
+
+                        MarketResponse::success(format!("Liquidity removed for {}", payout))
                     }
                     _ => MarketResponse::error("Market not found"),
                 }
             }
 
             Operation::SubmitOracleVote {
+                market_chain,
                 market_id,
                 outcome,
-                weight,
             } => {
-                let oracle = self.runtime.chain_id();
-                
                 self.runtime
-                    .prepare_message(Message::OracleVote {
-                        market_id,
-                        oracle,
-                        outcome,
-                        weight,
-                    })
-                    .send_to(self.runtime.chain_id());
+                    .prepare_message(Message::OracleVote { market_id, outcome })
+                    .send_to(market_chain);
 
                 MarketResponse::success("Oracle vote submitted")
             }
+
+            Operation::SetOracle {
+                application_id,
+                voting_period,
+            } => {
+                let signer = self.signer();
+                if self.state.admin.get().as_deref() != Some(signer.as_str()) {
+                    return MarketResponse::error("Only the admin can set the oracle");
+                }
+
+                self.state.oracle.set(Some(OracleLink {
+                    application_id,
+                    voting_period,
+                }));
+
+                MarketResponse::success("Oracle set")
+            }
+
+            Operation::OracleResolved {
+                market_id,
+                outcome,
+                value,
+            } => {
+                if !self.called_by_oracle() {
+                    return MarketResponse::error("Only the oracle application can report");
+                }
+                let Ok(Some(mut market)) = self.state.markets.get(&market_id).await else {
+                    return MarketResponse::error("Market not found");
+                };
+                if market.status != MarketStatus::Active || !market.oracle_pending {
+                    return MarketResponse::error("Market is not awaiting the oracle network");
+                }
+
+                let resolution = match (market.market_type, outcome) {
+                    (MarketType::Categorical, None) => Err("Categorical markets need an outcome"),
+                    (_, outcome) => market.resolution(outcome.unwrap_or_default(), value),
+                };
+                market.oracle_pending = false;
+                let (outcome, value) = match resolution {
+                    Ok(resolution) => resolution,
+                    Err(error) => {
+                        self.state.markets.insert(&market_id, market).unwrap();
+                        return MarketResponse::error(error);
+                    }
+                };
+                // Conditional markets still settle through `ResolveMarket` until the parent has
+                // resolved to their condition.
+                if self.parent_condition(&market).await != Some(true) {
+                    self.state.markets.insert(&market_id, market).unwrap();
+                    return MarketResponse::error(
+                        "Parent condition not met, manual resolution open",
+                    );
+                }
+
+                self.propose_resolution(market, outcome, value);
+
+                MarketResponse::success("Oracle resolution proposed")
+            }
+
+            Operation::OracleFailed { market_id } => {
+                if !self.called_by_oracle() {
+                    return MarketResponse::error("Only the oracle application can report");
+                }
+                let Ok(Some(mut market)) = self.state.markets.get(&market_id).await else {
+                    return MarketResponse::error("Market not found");
+                };
+
+                market.oracle_pending = false;
+                self.state.markets.insert(&market_id, market).unwrap();
+
+                MarketResponse::success("Oracle resolution failed, manual resolution open")
+            }
+
+            Operation::GetMarketData { market_id } => {
+                let Ok(Some(market)) = self.state.markets.get(&market_id).await else {
+                    return MarketResponse::error("Market not found");
+                };
+                let price_history = self
+                    .state
+                    .price_history
+                    .get(&market_id)
+                    .await
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+
+                MarketResponse::success("Market data").with_market_data(MarketData {
+                    market_id,
+                    status: market.status,
+                    odds: market.odds,
+                    total_volume: market.total_volume,
+                    liquidity: market.liquidity,
+                    price_history,
+                })
+            }
         }
     }
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            Message::OracleVote {
-                market_id,
-                oracle,
-                outcome,
-                weight,
-            } => {
+            Message::OracleVote { market_id, outcome } => {
+                let Some(oracle) = self.runtime.message_id().map(|id| id.chain_id) else {
+                    return;
+                };
+                let weight = match self.state.oracle_weights.get(&oracle).await {
+                    Ok(Some(weight)) if weight > 0 => weight,
+                    _ => return,
+                };
                 self.state
                     .oracle_votes
                     .insert(&(market_id, oracle), (outcome, weight))
                     .unwrap();
+                self.check_oracle_consensus(market_id).await;
             }
             Message::MarketResolved { .. } => {}
+            Message::MarketCancelled { .. } => {}
+            Message::SharesTransferred {
+                market_chain,
+                market_id,
+                option_index,
+                shares,
+                avg_price,
+                sender,
+                recipient,
+            } => {
+                let owner = if self.runtime.message_is_bouncing().unwrap_or_default() {
+                    sender
+                } else {
+                    if market_chain == self.runtime.chain_id() {
+                        assert!(
+                            matches!(self.state.markets.get(&market_id).await, Ok(Some(_))),
+                            "Market not found"
+                        );
+                    }
+                    owner_key(recipient)
+                };
+                let mut position = self
+                    .holding(market_chain, market_id, &owner, option_index)
+                    .await;
+                position.credit(shares, avg_price);
+                self.save_holding(market_chain, market_id, position);
+            }
             Message::TradeNotification { .. } => {}
         }
     }
@@ -234,3 +1050,322 @@ impl Contract for PredictionMarketContract {
         self.state.save().await.expect("Failed to save state");
     }
 }
+
+impl PredictionMarketContract {
+    fn next_market_id(&mut self) -> u64 {
+        let market_id = self.state.market_counter.get() + 1;
+        self.state.market_counter.set(market_id);
+        market_id
+    }
+
+    async fn position(&self, market_id: u64, owner: &str, option_index: u8) -> Position {
+        self.state
+            .positions
+            .get(&(market_id, owner.to_string(), option_index))
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| Position {
+                owner: owner.to_string(),
+                option_index,
+                shares: 0,
+                avg_price: 0,
+            })
+    }
+
+    fn save_position(&mut self, market_id: u64, position: Position) {
+        let key = (market_id, position.owner.clone(), position.option_index);
+        if position.shares == 0 {
+            self.state.positions.remove(&key).unwrap();
+        } else {
+            self.state.positions.insert(&key, position).unwrap();
+        }
+    }
+
+    /// Shares of `owner` in a market of `market_chain`: the position itself on the market's
+    /// chain, or shares transferred here from it.
+    async fn holding(
+        &self,
+        market_chain: ChainId,
+        market_id: u64,
+        owner: &str,
+        option_index: u8,
+    ) -> Position {
+        if market_chain == self.runtime.chain_id() {
+            return self.position(market_id, owner, option_index).await;
+        }
+        self.state
+            .remote_positions
+            .get(&(market_chain, market_id, owner.to_string(), option_index))
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_else(|| Position {
+                owner: owner.to_string(),
+                option_index,
+                shares: 0,
+                avg_price: 0,
+            })
+    }
+
+    fn save_holding(&mut self, market_chain: ChainId, market_id: u64, position: Position) {
+        if market_chain == self.runtime.chain_id() {
+            return self.save_position(market_id, position);
+        }
+        let key = (
+            market_chain,
+            market_id,
+            position.owner.clone(),
+            position.option_index,
+        );
+        if position.shares == 0 {
+            self.state.remote_positions.remove(&key).unwrap();
+        } else {
+            self.state.remote_positions.insert(&key, position).unwrap();
+        }
+    }
+
+    /// Starts the dispute window for a resolution of an active market.
+    fn propose_resolution(
+        &mut self,
+        mut market: MarketState,
+        outcome: Option<u8>,
+        value: Option<i64>,
+    ) {
+        let dispute_period = self.state.dispute_config.get().dispute_period;
+        let dispute_deadline = self.runtime.system_time().micros() as u64 + dispute_period;
+        let market_id = market.market_id;
+
+        market.status = MarketStatus::Proposed;
+        market.proposed_outcome = outcome;
+        market.proposed_value = value;
+        market.dispute_deadline = Some(dispute_deadline);
+        self.state.markets.insert(&market_id, market).unwrap();
+
+        self.runtime
+            .prepare_message(Message::ResolutionProposed {
+                market_id,
+                outcome,
+                value,
+                dispute_deadline,
+            })
+            .send_to(self.runtime.chain_id());
+    }
+
+    fn finalize_resolution(
+        &mut self,
+        market: &mut MarketState,
+        outcome: Option<u8>,
+        value: Option<i64>,
+    ) {
+        let market_id = market.market_id;
+        market.status = MarketStatus::Resolved;
+        market.resolved_outcome = outcome;
+        market.resolved_value = value;
+        self.state.markets.insert(&market_id, market.clone()).unwrap();
+
+        self.runtime
+            .prepare_message(Message::MarketResolved {
+                market_id,
+                outcome,
+                value,
+            })
+            .send_to(self.runtime.chain_id());
+    }
+
+    fn dispute_window_closed(&mut self, market: &MarketState) -> bool {
+        let now = self.runtime.system_time().micros() as u64;
+        market.dispute_deadline.is_some_and(|deadline| now >= deadline)
+    }
+
+    /// Proposes a resolution once the weighted oracle votes for one outcome reach
+    /// `oracle_threshold` percent.
+    async fn check_oracle_consensus(&mut self, market_id: u64) {
+        let Ok(Some(market)) = self.state.markets.get(&market_id).await else {
+            return;
+        };
+        if market.status != MarketStatus::Active || market.market_type != MarketType::Categorical {
+            return;
+        }
+
+        let mut weights = vec![0u128; market.options.len()];
+        self.state
+            .oracle_votes
+            .for_each_index_value(|(id, _), (outcome, weight)| {
+                if id == market_id {
+                    if let Some(total) = weights.get_mut(outcome as usize) {
+                        *total += u128::from(weight);
+                    }
+                }
+                Ok(())
+            })
+            .await
+            .unwrap();
+
+        let total: u128 = weights.iter().sum();
+        let threshold = u128::from(*self.state.oracle_threshold.get());
+        if total == 0 {
+            return;
+        }
+        if let Some(outcome) = (0..weights.len()).find(|&i| weights[i] * 100 >= threshold * total) {
+            if self.parent_condition(&market).await == Some(true) {
+                self.propose_resolution(market, Some(outcome as u8), None);
+            }
+        }
+    }
+
+    async fn credit_lp_shares(&mut self, market_id: u64, provider: &str, shares: u64) {
+        if shares == 0 {
+            return;
+        }
+        let key = (market_id, provider.to_string());
+        let balance = self
+            .state
+            .lp_shares
+            .get(&key)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        self.state.lp_shares.insert(&key, balance + shares).unwrap();
+    }
+
+    /// Appends the market's current odds to its price history, keeping the latest
+    /// `PRICE_HISTORY_LEN` points, and publishes them to subscribers.
+    async fn record_price(&mut self, market_id: u64, odds: Vec<u64>) {
+        let timestamp = self.runtime.system_time().micros();
+        self.runtime.emit(
+            StreamName(MARKET_UPDATES_STREAM.to_vec()),
+            &MarketEvent::Updated(MarketUpdate {
+                market_id,
+                odds: odds.clone(),
+                timestamp,
+            }),
+        );
+
+        let mut history = self
+            .state
+            .price_history
+            .get(&market_id)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        history.push(PricePoint { timestamp, odds });
+        if history.len() > PRICE_HISTORY_LEN {
+            history.drain(..history.len() - PRICE_HISTORY_LEN);
+        }
+        self.state.price_history.insert(&market_id, history).unwrap();
+    }
+
+    async fn credit_fees(&mut self, recipient: &str, amount: u64) {
+        if amount == 0 {
+            return;
+        }
+        let balance = self
+            .state
+            .fee_balances
+            .get(recipient)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        self.state
+            .fee_balances
+            .insert(recipient, balance + amount)
+            .unwrap();
+    }
+
+    async fn fee_balance(&self, owner: &str) -> u64 {
+        self.state
+            .fee_balances
+            .get(owner)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    /// Takes up to `amount` from a fee balance, returning what was taken.
+    async fn debit_fees(&mut self, owner: &str, amount: u64) -> u64 {
+        let balance = self.fee_balance(owner).await;
+        let taken = balance.min(amount);
+        self.state
+            .fee_balances
+            .insert(owner, balance - taken)
+            .unwrap();
+        taken
+    }
+
+    /// Whether the parent condition of a conditional market holds, or `None` while the
+    /// parent is still unresolved. Unconditional markets always hold.
+    async fn parent_condition(&self, market: &MarketState) -> Option<bool> {
+        let (Some(parent_id), Some(parent_outcome)) =
+            (market.parent_market_id, market.parent_outcome)
+        else {
+            return Some(true);
+        };
+        match self.state.markets.get(&parent_id).await.ok().flatten() {
+            Some(parent) if parent.status == MarketStatus::Resolved => {
+                Some(parent.resolved_outcome == Some(parent_outcome))
+            }
+            Some(parent) if parent.status == MarketStatus::Cancelled => Some(false),
+            Some(_) => None,
+            None => Some(false),
+        }
+    }
+
+    /// Ledger key of the caller. Calls from another application are booked under that
+    /// application, whichever signer submitted the block, so a trading bot holds one set of
+    /// positions for its owner, operators and scheduled runs.
+    fn signer(&mut self) -> String {
+        match self.runtime.authenticated_caller_id() {
+            Some(application_id) => format!("app:{application_id}"),
+            None => owner_key(self.runtime.authenticated_signer()),
+        }
+    }
+
+    /// Asks the configured oracle network to resolve a market that has a resolution time.
+    /// Returns whether a request was published.
+    fn request_resolution(&mut self, market: &MarketState) -> bool {
+        let (Some(oracle), Some(resolution_time)) =
+            (self.state.oracle.get().clone(), market.resolution_time)
+        else {
+            return false;
+        };
+        let (options, unit) = match &market.scalar_range {
+            Some(range) => (Vec::new(), Some(range.unit.clone())),
+            None => (market.options.clone(), None),
+        };
+        self.runtime.emit(
+            StreamName(RESOLUTION_REQUESTS_STREAM.to_vec()),
+            &MarketEvent::ResolutionRequested(ResolutionQuery {
+                market_id: market.market_id,
+                question: market.title.clone(),
+                options,
+                unit,
+                decimals: 0,
+                deadline: resolution_time.saturating_add(oracle.voting_period),
+            }),
+        );
+        true
+    }
+
+    /// Whether the current call comes from the configured oracle application.
+    fn called_by_oracle(&mut self) -> bool {
+        let oracle = self.state.oracle.get().as_ref().map(|link| link.application_id);
+        oracle.is_some() && self.runtime.authenticated_caller_id() == oracle
+    }
+}
+
+/// Refund the market no longer owes once `shares` leave `position`, at its entry price.
+fn refund_for_sold(shares: u64, position: &Position) -> u64 {
+    ((shares as u128 * position.avg_price as u128) / PRECISION as u128) as u64
+}
+
+/// Key under which an owner's positions are stored in the position ledger.
+fn owner_key(owner: Option<Owner>) -> String {
+    owner
+        .map(|o| o.to_string())
+        .unwrap_or_else(|| "anonymous".to_string())
+}
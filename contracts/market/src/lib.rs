@@ -1,6 +1,6 @@
 use async_graphql::{Request, Response, SimpleObject};
 use linera_sdk::{
-    base::{Amount, ChainId, ContractAbi, Owner, ServiceAbi},
+    base::{Account, Amount, ApplicationId, ChainId, ContractAbi, Owner, ServiceAbi},
     graphql::GraphQLMutationRoot,
 };
 use serde::{Deserialize, Serialize};
@@ -23,25 +23,114 @@ pub struct MarketState {
     pub title: String,
     pub description: String,
     pub category: String,
+    pub market_type: MarketType,
     pub options: Vec<String>,
+    pub scalar_range: Option<ScalarRange>,
     pub odds: Vec<u64>,
     pub total_volume: u64,
     pub liquidity: u64,
     pub status: MarketStatus,
     pub resolved_outcome: Option<u8>,
+    pub resolved_value: Option<i64>,
+    /// Resolution awaiting the end of its dispute window.
+    pub proposed_outcome: Option<u8>,
+    pub proposed_value: Option<i64>,
+    pub dispute_deadline: Option<u64>,
+    pub disputer: Option<String>,
+    pub disputed_outcome: Option<u8>,
+    pub disputed_value: Option<i64>,
+    pub dispute_bond: u64,
     pub created_at: u64,
     pub resolution_time: Option<u64>,
     pub trade_count: u64,
+    pub creator: String,
+    /// Per-market trading fee set by the creator, overriding the default fee.
+    pub fee_bps: Option<u64>,
+    /// Share of trading fees accrued to the market's liquidity providers.
+    pub lp_fees: u64,
+    /// Complete sets minted and not yet redeemed or deposited. Informational: redemption
+    /// is limited by the redeemer's shares and the market's collateral.
+    pub complete_sets: u64,
+    /// Collateral held by the market: liquidity deposits, trade proceeds, complete-set
+    /// collateral and LP fees, minus payouts and withdrawals.
+    pub collateral: u64,
+    /// Shares held by traders for each option, which the market owes at resolution.
+    pub outstanding_shares: Vec<u64>,
+    /// Cost basis of the outstanding shares of each option, rounded up: what the market
+    /// refunds if it is cancelled.
+    pub outstanding_cost: Vec<u64>,
+    pub total_lp_shares: u64,
+    /// Conditional markets only settle if `parent_market_id` resolves to `parent_outcome`,
+    /// and are refunded otherwise.
+    pub parent_market_id: Option<u64>,
+    pub parent_outcome: Option<u8>,
+    /// Set while the oracle network has an open request for this market, during which
+    /// `ResolveMarket` is rejected.
+    pub oracle_pending: bool,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, SimpleObject)]
 pub enum MarketStatus {
     #[default]
     Active,
+    /// A resolution was proposed and can be disputed until `dispute_deadline`.
+    Proposed,
+    /// The proposed resolution was challenged and awaits arbitration.
+    Disputed,
     Resolved,
     Cancelled,
 }
 
+impl MarketState {
+    /// Checks a resolution against the market type, returning the outcome or value to record.
+    pub fn resolution(
+        &self,
+        outcome: u8,
+        value: Option<i64>,
+    ) -> Result<(Option<u8>, Option<i64>), &'static str> {
+        match self.market_type {
+            MarketType::Scalar => match value {
+                Some(value) => Ok((None, Some(value))),
+                None => Err("Scalar markets must be resolved with a value"),
+            },
+            MarketType::Categorical => {
+                if outcome as usize >= self.options.len() {
+                    return Err("Invalid outcome");
+                }
+                Ok((Some(outcome), None))
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, async_graphql::Enum)]
+pub enum MarketType {
+    #[default]
+    Categorical,
+    Scalar,
+}
+
+/// Numeric range of a scalar market. LONG shares pay out linearly from 0 at
+/// `lower_bound` to 1 at `upper_bound`; SHORT shares pay the complement.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct ScalarRange {
+    pub lower_bound: i64,
+    pub upper_bound: i64,
+    pub unit: String,
+}
+
+impl ScalarRange {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.upper_bound <= self.lower_bound {
+            return Err("Upper bound must be above lower bound");
+        }
+        if self.unit.trim().is_empty() {
+            return Err("Scalar markets need a unit");
+        }
+        Ok(())
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
 pub struct Trade {
     pub trade_id: u64,
@@ -61,6 +150,86 @@ pub struct Position {
     pub avg_price: u64,
 }
 
+impl Position {
+    /// Adds `shares` acquired at `price`, updating the average entry price.
+    pub fn credit(&mut self, shares: u64, price: u64) {
+        let cost = self.shares as u128 * self.avg_price as u128 + shares as u128 * price as u128;
+        self.shares += shares;
+        if self.shares > 0 {
+            self.avg_price = (cost / self.shares as u128) as u64;
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct LiquidityPosition {
+    pub market_id: u64,
+    pub owner: String,
+    pub shares: u64,
+    pub value: u64,
+}
+
+/// Odds of a market after a trade, scaled by `PRECISION`.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct PricePoint {
+    pub timestamp: u64,
+    pub odds: Vec<u64>,
+}
+
+/// Event published on `MARKET_UPDATES_STREAM` after every trade, odds scaled by `PRECISION`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MarketUpdate {
+    pub market_id: u64,
+    pub odds: Vec<u64>,
+    pub timestamp: u64,
+}
+
+/// Event stream other applications subscribe to for market updates.
+pub const MARKET_UPDATES_STREAM: &[u8] = b"market_updates";
+
+/// Event stream the oracle network subscribes to for resolution requests.
+pub const RESOLUTION_REQUESTS_STREAM: &[u8] = b"resolution_requests";
+
+/// Events published by the market: `Updated` on `MARKET_UPDATES_STREAM` and
+/// `ResolutionRequested` on `RESOLUTION_REQUESTS_STREAM`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum MarketEvent {
+    Updated(MarketUpdate),
+    ResolutionRequested(ResolutionQuery),
+}
+
+/// Asks the oracle network to resolve a market. Categorical markets list their `options`;
+/// scalar markets leave them empty and give the `unit` and `decimals` of the value.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResolutionQuery {
+    pub market_id: u64,
+    pub question: String,
+    pub options: Vec<String>,
+    pub unit: Option<String>,
+    pub decimals: u8,
+    /// Oracles may commit votes until this time, in microseconds.
+    pub deadline: u64,
+}
+
+/// Oracle application asked to resolve markets created with a resolution time.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct OracleLink {
+    pub application_id: ApplicationId,
+    /// Microseconds after a market's resolution time during which oracles may commit votes.
+    pub voting_period: u64,
+}
+
+/// Snapshot of a market returned to calling applications by `GetMarketData`.
+#[derive(Clone, Debug, Deserialize, Serialize, SimpleObject)]
+pub struct MarketData {
+    pub market_id: u64,
+    pub status: MarketStatus,
+    pub odds: Vec<u64>,
+    pub total_volume: u64,
+    pub liquidity: u64,
+    pub price_history: Vec<PricePoint>,
+}
+
 #[derive(Debug, Deserialize, Serialize, GraphQLMutationRoot)]
 pub enum Operation {
     CreateMarket {
@@ -70,6 +239,18 @@ pub enum Operation {
         options: Vec<String>,
         initial_liquidity: u64,
         resolution_time: Option<u64>,
+        parent_market_id: Option<u64>,
+        parent_outcome: Option<u8>,
+    },
+    CreateScalarMarket {
+        title: String,
+        description: String,
+        category: String,
+        lower_bound: i64,
+        upper_bound: i64,
+        unit: String,
+        initial_liquidity: u64,
+        resolution_time: Option<u64>,
     },
     PlaceTrade {
         market_id: u64,
@@ -80,18 +261,107 @@ pub enum Operation {
     ResolveMarket {
         market_id: u64,
         outcome: u8,
+        value: Option<i64>,
+    },
+    /// Challenges a proposed resolution during its dispute window. `bond` is posted with
+    /// the dispute and must cover `DisputeConfig::dispute_bond`; it is returned to the fee
+    /// balance of a successful challenger and forfeited otherwise.
+    DisputeResolution {
+        market_id: u64,
+        outcome: u8,
+        value: Option<i64>,
+        bond: u64,
+    },
+    /// Settles a disputed market. Only the admin may arbitrate.
+    ArbitrateDispute {
+        market_id: u64,
+        outcome: u8,
+        value: Option<i64>,
+    },
+    /// Finalises an undisputed resolution once its dispute window has closed.
+    FinalizeResolution {
+        market_id: u64,
     },
     ClaimPayout {
         market_id: u64,
     },
+    /// Locks `amount` collateral and credits one share of every outcome per unit.
+    MintCompleteSet {
+        market_id: u64,
+        amount: u64,
+    },
+    /// Burns `amount` shares of every outcome and releases one unit of collateral per set.
+    /// Any full set held counts, whether minted or assembled through trades.
+    RedeemCompleteSet {
+        market_id: u64,
+        amount: u64,
+    },
+    /// Moves `amount` shares to another owner, on this chain or on another chain. Shares
+    /// of a market on another chain are held under `market_chain` and can be moved on,
+    /// but only claimed once transferred back to the market's chain.
+    TransferShares {
+        /// Chain of the market; this chain when unset.
+        #[serde(default)]
+        market_chain: Option<ChainId>,
+        market_id: u64,
+        option_index: u8,
+        amount: u64,
+        to: Account,
+    },
+    /// Overrides the trading fee of a market. Only the market creator may call this, so
+    /// markets created without a signer keep the default fee, and the fee cannot exceed
+    /// `FeeConfig::max_market_fee_bps`.
+    SetMarketFee {
+        market_id: u64,
+        fee_bps: u64,
+    },
+    /// Withdraws the caller's accrued protocol or creator fees, returned as the payout.
+    WithdrawFees,
+    /// Deposits collateral into a market and mints LP shares against it.
     AddLiquidity {
         market_id: u64,
         amount: u64,
     },
+    /// Deposits `sets` complete sets held by the caller into the pool and mints LP shares
+    /// against them, each set counting as one unit of collateral.
+    AddLiquidityFromSets {
+        market_id: u64,
+        sets: u64,
+    },
+    /// Burns LP shares for their share of the pool value, before or after resolution.
+    /// Before resolution the pool keeps back the largest payout traders could claim.
+    RemoveLiquidity {
+        market_id: u64,
+        shares: u64,
+    },
+    /// Votes for an outcome of a market on `market_chain`. Only chains listed in
+    /// `oracle_weights` are counted, with their configured weight.
     SubmitOracleVote {
+        market_chain: ChainId,
         market_id: u64,
         outcome: u8,
-        weight: u64,
+    },
+    /// Sets the oracle application that resolves markets created with a resolution time
+    /// from then on. Only the admin may set it.
+    SetOracle {
+        application_id: ApplicationId,
+        voting_period: u64,
+    },
+    /// Proposes the resolution reached by the oracle network: `outcome` for categorical
+    /// markets, `value` for scalar ones. Only the oracle application may call this.
+    OracleResolved {
+        market_id: u64,
+        outcome: Option<u8>,
+        value: Option<i64>,
+    },
+    /// Reports that the oracle network could not resolve the market, which opens
+    /// `ResolveMarket`. Only the oracle application may call this.
+    OracleFailed {
+        market_id: u64,
+    },
+    /// Returns the market's odds, volume and price history in the response.
+    GetMarketData {
+        market_id: u64,
     },
 }
 
@@ -99,13 +369,36 @@ pub enum Operation {
 pub enum Message {
     OracleVote {
         market_id: u64,
-        oracle: ChainId,
         outcome: u8,
-        weight: u64,
     },
     MarketResolved {
         market_id: u64,
-        outcome: u8,
+        outcome: Option<u8>,
+        value: Option<i64>,
+    },
+    MarketCancelled {
+        market_id: u64,
+    },
+    ResolutionProposed {
+        market_id: u64,
+        outcome: Option<u8>,
+        value: Option<i64>,
+        dispute_deadline: u64,
+    },
+    ResolutionDisputed {
+        market_id: u64,
+        disputer: String,
+    },
+    /// Shares of a market on `market_chain` sent from `sender` on another chain. Credited
+    /// back to the sender if bounced.
+    SharesTransferred {
+        market_chain: ChainId,
+        market_id: u64,
+        option_index: u8,
+        shares: u64,
+        avg_price: u64,
+        sender: String,
+        recipient: Option<Owner>,
     },
     TradeNotification {
         market_id: u64,
@@ -121,6 +414,13 @@ pub struct MarketResponse {
     pub success: bool,
     pub market_id: Option<u64>,
     pub trade_id: Option<u64>,
+    /// Execution price and shares of a trade, for calling applications.
+    pub price: Option<u64>,
+    pub shares: Option<u64>,
+    /// Collateral paid out to the caller: winnings of `ClaimPayout`, fees withdrawn by
+    /// `WithdrawFees`, or the proceeds of a sell net of fees.
+    pub payout: Option<u64>,
+    pub market_data: Option<MarketData>,
     pub message: String,
 }
 
@@ -130,6 +430,10 @@ impl MarketResponse {
             success: true,
             market_id: None,
             trade_id: None,
+            price: None,
+            shares: None,
+            payout: None,
+            market_data: None,
             message: message.into(),
         }
     }
@@ -144,11 +448,31 @@ impl MarketResponse {
         self
     }
 
+    pub fn with_fill(mut self, price: u64, shares: u64) -> Self {
+        self.price = Some(price);
+        self.shares = Some(shares);
+        self
+    }
+
+    pub fn with_payout(mut self, payout: u64) -> Self {
+        self.payout = Some(payout);
+        self
+    }
+
+    pub fn with_market_data(mut self, data: MarketData) -> Self {
+        self.market_data = Some(data);
+        self
+    }
+
     pub fn error(message: impl Into<String>) -> Self {
         Self {
             success: false,
             market_id: None,
             trade_id: None,
+            price: None,
+            shares: None,
+            payout: None,
+            market_data: None,
             message: message.into(),
         }
     }
@@ -158,9 +482,161 @@ impl MarketResponse {
 pub struct InstantiationArgument {
     pub admin: Option<String>,
     pub oracle_threshold: u64,
+    /// Chains allowed to submit oracle votes, with the weight of their votes.
+    #[serde(default)]
+    pub oracle_weights: Vec<(ChainId, u64)>,
+    #[serde(default)]
+    pub fee_config: FeeConfig,
+    #[serde(default)]
+    pub dispute_config: DisputeConfig,
+}
+
+/// Dispute window applied to every proposed resolution.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct DisputeConfig {
+    /// Microseconds during which a proposed resolution can be disputed.
+    pub dispute_period: u64,
+    /// Smallest bond a disputer must post with `DisputeResolution`.
+    pub dispute_bond: u64,
+    /// Paid from the treasury's fee balance to a successful challenger.
+    pub dispute_reward: u64,
+}
+
+/// Trading fee settings. Fees are split between the protocol treasury, the market
+/// creator and the market's liquidity providers, who receive the remainder.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, SimpleObject)]
+pub struct FeeConfig {
+    pub trading_fee_bps: u64,
+    pub max_market_fee_bps: u64,
+    pub protocol_share_bps: u64,
+    pub creator_share_bps: u64,
+    /// Recipient of protocol fees. Defaults to the admin.
+    pub treasury: Option<String>,
+}
+
+impl FeeConfig {
+    pub fn validate(&self) -> Result<(), &'static str> {
+        if self.trading_fee_bps > BPS_DENOMINATOR || self.max_market_fee_bps > BPS_DENOMINATOR {
+            return Err("Trading fees exceed 100%");
+        }
+        if self.protocol_share_bps + self.creator_share_bps > BPS_DENOMINATOR {
+            return Err("Fee shares exceed 100%");
+        }
+        Ok(())
+    }
+
+    /// Splits `fee` into its protocol, creator and liquidity provider parts.
+    pub fn split(&self, fee: u64) -> (u64, u64, u64) {
+        let protocol = fee_for_amount(fee, self.protocol_share_bps);
+        let creator = fee_for_amount(fee, self.creator_share_bps);
+        let lp = fee.saturating_sub(protocol + creator);
+        (protocol, creator, lp)
+    }
 }
 
 pub const PRECISION: u64 = 1_000_000;
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Number of price points kept per market.
+pub const PRICE_HISTORY_LEN: usize = 100;
+
+pub const LONG_INDEX: u8 = 0;
+pub const SHORT_INDEX: u8 = 1;
+
+/// Number of shares bought by `amount` of collateral at `price` (scaled by `PRECISION`).
+/// A share pays out one unit of collateral if its outcome wins.
+pub fn shares_for_amount(amount: u64, price: u64) -> u64 {
+    if price == 0 {
+        return 0;
+    }
+    ((amount as u128 * PRECISION as u128) / price as u128) as u64
+}
+
+/// Fee of `fee_bps` basis points charged on `amount`.
+pub fn fee_for_amount(amount: u64, fee_bps: u64) -> u64 {
+    ((amount as u128 * fee_bps as u128) / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Payout of a LONG share of a scalar market resolved at `value`, scaled by `PRECISION`.
+pub fn scalar_long_payout(range: &ScalarRange, value: i64) -> u64 {
+    if range.upper_bound <= range.lower_bound {
+        return PRECISION / 2;
+    }
+    // Bounds may span the whole i64 range, so differences are taken in i128.
+    let clamped = value.clamp(range.lower_bound, range.upper_bound);
+    let offset = (clamped as i128 - range.lower_bound as i128) as u128;
+    let width = (range.upper_bound as i128 - range.lower_bound as i128) as u128;
+    ((offset * PRECISION as u128) / width) as u64
+}
+
+/// Collateral returned for a position when its market is cancelled: the remaining cost basis.
+pub fn refund_for_position(position: &Position) -> u64 {
+    ((position.shares as u128 * position.avg_price as u128) / PRECISION as u128) as u64
+}
+
+/// Cost of `shares` bought at `price`, rounded up so that the tracked cost basis of a
+/// market never falls short of the refunds it owes.
+pub fn cost_of_shares(shares: u64, price: u64) -> u64 {
+    (shares as u128 * price as u128).div_ceil(PRECISION as u128) as u64
+}
+
+/// Collateral the market may still have to pay traders. Resolved markets owe the payout
+/// of the outstanding shares and cancelled ones their cost basis. Before resolution the
+/// market reserves the worst case: every share of the most held outcome winning, or a
+/// cancellation refunding every position.
+pub fn market_liability(market: &MarketState) -> u64 {
+    let refunds: u64 = market.outstanding_cost.iter().sum();
+    match market.status {
+        MarketStatus::Resolved => market
+            .outstanding_shares
+            .iter()
+            .enumerate()
+            .map(|(i, shares)| {
+                let per_share = payout_per_share(market, i as u8);
+                (*shares as u128 * per_share as u128 / PRECISION as u128) as u64
+            })
+            .sum(),
+        MarketStatus::Cancelled => refunds,
+        _ => {
+            let winner = market.outstanding_shares.iter().copied().max().unwrap_or(0);
+            winner.max(refunds)
+        }
+    }
+}
+
+/// Collateral attributable to liquidity providers: the market's collateral minus what it
+/// may owe traders, see `market_liability`.
+pub fn lp_pool_value(market: &MarketState) -> u64 {
+    market.collateral.saturating_sub(market_liability(market))
+}
+
+/// LP shares minted for a deposit of `amount` into a pool worth `pool_value`. `None` when
+/// the pool is drained but still has shares, which would otherwise capture the deposit.
+pub fn lp_shares_for_deposit(amount: u64, pool_value: u64, total_shares: u64) -> Option<u64> {
+    if total_shares == 0 {
+        return Some(amount);
+    }
+    if pool_value == 0 {
+        return None;
+    }
+    Some(((amount as u128 * total_shares as u128) / pool_value as u128) as u64)
+}
+
+/// Payout of one share of `option_index` in a resolved market, scaled by `PRECISION`.
+pub fn payout_per_share(market: &MarketState, option_index: u8) -> u64 {
+    match (market.market_type, &market.scalar_range, market.resolved_value) {
+        (MarketType::Scalar, Some(range), Some(value)) => {
+            let long = scalar_long_payout(range, value);
+            if option_index == LONG_INDEX {
+                long
+            } else {
+                PRECISION - long
+            }
+        }
+        _ if market.resolved_outcome == Some(option_index) => PRECISION,
+        _ => 0,
+    }
+}
 
 pub fn calculate_amm_price(yes_shares: u64, no_shares: u64, is_yes: bool) -> u64 {
     let total = yes_shares + no_shares;
@@ -4,11 +4,14 @@ mod state;
 
 use async_graphql::{EmptySubscription, Object, Request, Response, Schema};
 use linera_sdk::{
-    base::WithServiceAbi,
+    base::{ApplicationId, ChainId, WithServiceAbi},
     views::{RootView, View},
     Service, ServiceRuntime,
 };
-use prediction_market::{MarketState, Operation, PredictionMarketAbi};
+use prediction_market::{
+    lp_pool_value, DisputeConfig, FeeConfig, LiquidityPosition, MarketState, Operation, OracleLink,
+    Position, PredictionMarketAbi, PricePoint,
+};
 use state::ApplicationState;
 use std::sync::Arc;
 
@@ -84,6 +87,97 @@ impl QueryRoot {
         }
         result
     }
+
+    async fn fee_config(&self) -> FeeConfig {
+        self.state.fee_config.get().clone()
+    }
+
+    async fn dispute_config(&self) -> DisputeConfig {
+        self.state.dispute_config.get().clone()
+    }
+
+    async fn oracle(&self) -> Option<OracleLink> {
+        self.state.oracle.get().clone()
+    }
+
+    async fn fee_balance(&self, owner: String) -> u64 {
+        self.state
+            .fee_balances
+            .get(&owner)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    async fn liquidity_position(&self, market_id: u64, owner: String) -> Option<LiquidityPosition> {
+        let market = self.state.markets.get(&market_id).await.ok().flatten()?;
+        let shares = self
+            .state
+            .lp_shares
+            .get(&(market_id, owner.clone()))
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let value = if market.total_lp_shares == 0 {
+            0
+        } else {
+            ((lp_pool_value(&market) as u128 * shares as u128) / market.total_lp_shares as u128)
+                as u64
+        };
+        Some(LiquidityPosition {
+            market_id,
+            owner,
+            shares,
+            value,
+        })
+    }
+
+    async fn price_history(&self, market_id: u64) -> Vec<PricePoint> {
+        self.state
+            .price_history
+            .get(&market_id)
+            .await
+            .ok()
+            .flatten()
+            .unwrap_or_default()
+    }
+
+    async fn positions(&self, market_id: u64, owner: String) -> Vec<Position> {
+        let Ok(Some(market)) = self.state.markets.get(&market_id).await else {
+            return Vec::new();
+        };
+        let mut result = Vec::new();
+        for option_index in 0..market.options.len() as u8 {
+            let key = (market_id, owner.clone(), option_index);
+            if let Ok(Some(position)) = self.state.positions.get(&key).await {
+                result.push(position);
+            }
+        }
+        result
+    }
+
+    /// Shares of a market on `market_chain` that were transferred to `owner` on this chain.
+    async fn remote_positions(
+        &self,
+        market_chain: ChainId,
+        market_id: u64,
+        owner: String,
+    ) -> Vec<Position> {
+        let mut result = Vec::new();
+        self.state
+            .remote_positions
+            .for_each_index_value(|(chain, id, holder, _), position| {
+                if chain == market_chain && id == market_id && holder == owner {
+                    result.push(position);
+                }
+                Ok(())
+            })
+            .await
+            .unwrap_or_default();
+        result
+    }
 }
 
 struct MutationRoot;
@@ -113,18 +207,166 @@ impl MutationRoot {
         category: String,
         options: Vec<String>,
         initial_liquidity: u64,
+        parent_market_id: Option<u64>,
+        parent_outcome: Option<u8>,
     ) -> async_graphql::Result<String> {
-        Ok(format!("Market creation operation: {}", title))
+        match (parent_market_id, parent_outcome) {
+            (Some(parent_id), Some(outcome)) => Ok(format!(
+                "Market creation operation: {} (if market {} resolves to {})",
+                title, parent_id, outcome
+            )),
+            _ => Ok(format!("Market creation operation: {}", title)),
+        }
     }
 
-    async fn resolve_market(
+    async fn create_scalar_market(
+        &self,
+        title: String,
+        description: String,
+        category: String,
+        lower_bound: i64,
+        upper_bound: i64,
+        unit: String,
+        initial_liquidity: u64,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Scalar market creation operation: {} [{}, {}] {}",
+            title, lower_bound, upper_bound, unit
+        ))
+    }
+
+    async fn mint_complete_set(&self, market_id: u64, amount: u64) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Mint complete set operation for market {} amount {}",
+            market_id, amount
+        ))
+    }
+
+    async fn redeem_complete_set(
+        &self,
+        market_id: u64,
+        amount: u64,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Redeem complete set operation for market {} amount {}",
+            market_id, amount
+        ))
+    }
+
+    async fn transfer_shares(
+        &self,
+        market_chain: Option<String>,
+        market_id: u64,
+        option_index: u8,
+        amount: u64,
+        to_chain: String,
+        to_owner: Option<String>,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Transfer operation for market {}{} option {} amount {} to {}:{}",
+            market_chain.map(|chain| format!("{chain}:")).unwrap_or_default(),
+            market_id,
+            option_index,
+            amount,
+            to_chain,
+            to_owner.unwrap_or_default()
+        ))
+    }
+
+    async fn set_market_fee(&self, market_id: u64, fee_bps: u64) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Set fee operation for market {} to {} bps",
+            market_id, fee_bps
+        ))
+    }
+
+    async fn withdraw_fees(&self) -> async_graphql::Result<String> {
+        Ok("Withdraw fees operation".to_string())
+    }
+
+    async fn add_liquidity(&self, market_id: u64, amount: u64) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Add liquidity operation for market {} amount {}",
+            market_id, amount
+        ))
+    }
+
+    async fn add_liquidity_from_sets(
+        &self,
+        market_id: u64,
+        sets: u64,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Add liquidity from complete sets operation for market {} sets {}",
+            market_id, sets
+        ))
+    }
+
+    async fn remove_liquidity(&self, market_id: u64, shares: u64) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Remove liquidity operation for market {} shares {}",
+            market_id, shares
+        ))
+    }
+
+    async fn dispute_resolution(
+        &self,
+        market_id: u64,
+        outcome: u8,
+        value: Option<i64>,
+        bond: u64,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Dispute operation for market {} proposing {} with bond {}",
+            market_id,
+            value.map_or(outcome.to_string(), |value| value.to_string()),
+            bond
+        ))
+    }
+
+    async fn arbitrate_dispute(
         &self,
         market_id: u64,
         outcome: u8,
+        value: Option<i64>,
+    ) -> async_graphql::Result<String> {
+        Ok(format!(
+            "Arbitration operation for market {} with {}",
+            market_id,
+            value.map_or(outcome.to_string(), |value| value.to_string())
+        ))
+    }
+
+    async fn set_oracle(
+        &self,
+        application_id: ApplicationId,
+        voting_period: u64,
     ) -> async_graphql::Result<String> {
         Ok(format!(
-            "Resolution operation for market {} with outcome {}",
-            market_id, outcome
+            "Set oracle operation for application {} with voting period {}",
+            application_id, voting_period
         ))
     }
+
+    async fn finalize_resolution(&self, market_id: u64) -> async_graphql::Result<String> {
+        Ok(format!("Finalize resolution operation for market {}", market_id))
+    }
+
+    async fn resolve_market(
+        &self,
+        market_id: u64,
+        outcome: u8,
+        value: Option<i64>,
+    ) -> async_graphql::Result<String> {
+        match value {
+            Some(value) => Ok(format!(
+                "Resolution operation for market {} with value {}",
+                market_id, value
+            )),
+            None => Ok(format!(
+                "Resolution operation for market {} with outcome {}",
+                market_id, outcome
+            )),
+        }
+    }
 }
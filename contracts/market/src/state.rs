@@ -2,14 +2,25 @@ use linera_sdk::{
     base::ChainId,
     views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
 };
-use prediction_market::MarketState;
+use prediction_market::{DisputeConfig, FeeConfig, MarketState, OracleLink, Position, PricePoint};
 
 #[derive(RootView, async_graphql::SimpleObject)]
 #[view(context = "ViewStorageContext")]
 pub struct ApplicationState {
     pub admin: RegisterView<Option<String>>,
     pub oracle_threshold: RegisterView<u64>,
+    pub oracle_weights: MapView<ChainId, u64>,
+    pub oracle: RegisterView<Option<OracleLink>>,
+    pub fee_config: RegisterView<FeeConfig>,
+    pub dispute_config: RegisterView<DisputeConfig>,
     pub market_counter: RegisterView<u64>,
     pub markets: MapView<u64, MarketState>,
     pub oracle_votes: MapView<(u64, ChainId), (u8, u64)>,
+    pub positions: MapView<(u64, String, u8), Position>,
+    /// Shares of markets on other chains, transferred here, by market chain, market id,
+    /// owner and option.
+    pub remote_positions: MapView<(ChainId, u64, String, u8), Position>,
+    pub fee_balances: MapView<String, u64>,
+    pub lp_shares: MapView<(u64, String), u64>,
+    pub price_history: MapView<u64, Vec<PricePoint>>,
 }
@@ -0,0 +1,402 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A vote as seen by a consensus rule. Discrete votes report their outcome index as `value`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ballot {
+    pub outcome: u8,
+    pub value: f64,
+    pub confidence: f64,
+    pub stake: f64,
+    pub reputation: f64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Consensus {
+    Outcome(u8),
+    Value(f64),
+}
+
+/// Aggregates revealed ballots into a consensus. `abstentions` are the active oracles
+/// that did not vote; rules that measure support against the whole oracle set count them.
+pub trait ConsensusRule {
+    fn tally(&self, ballots: &[Ballot], abstentions: &[Ballot]) -> Option<Consensus>;
+}
+
+/// Consensus rule selected in `OracleParameters`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub enum ConsensusRuleKind {
+    SimpleMajority,
+    StakeWeightedSupermajority {
+        threshold: f64,
+    },
+    #[default]
+    ReputationWeighted,
+    Median,
+    TrimmedMean {
+        trim_fraction: f64,
+    },
+}
+
+impl ConsensusRuleKind {
+    pub fn rule(&self, required_consensus: f64) -> Box<dyn ConsensusRule> {
+        match self {
+            ConsensusRuleKind::SimpleMajority => Box::new(SimpleMajority),
+            ConsensusRuleKind::StakeWeightedSupermajority { threshold } => {
+                Box::new(StakeWeightedSupermajority { threshold: *threshold })
+            }
+            ConsensusRuleKind::ReputationWeighted => Box::new(ReputationWeighted {
+                threshold: required_consensus,
+            }),
+            ConsensusRuleKind::Median => Box::new(Median),
+            ConsensusRuleKind::TrimmedMean { trim_fraction } => Box::new(TrimmedMean {
+                trim_fraction: *trim_fraction,
+            }),
+        }
+    }
+
+    /// Whether the rule averages reported values rather than picking an outcome. Such
+    /// rules cannot resolve discrete requests.
+    pub fn is_value_based(&self) -> bool {
+        matches!(
+            self,
+            ConsensusRuleKind::Median | ConsensusRuleKind::TrimmedMean { .. }
+        )
+    }
+}
+
+/// One oracle, one vote: an outcome wins with more than half of all active oracles.
+pub struct SimpleMajority;
+
+impl ConsensusRule for SimpleMajority {
+    fn tally(&self, ballots: &[Ballot], abstentions: &[Ballot]) -> Option<Consensus> {
+        let total = (ballots.len() + abstentions.len()) as f64;
+        weighted_plurality(ballots, total, |_| 1.0)
+            .filter(|(_, weight)| *weight * 2.0 > total)
+            .map(|(outcome, _)| Consensus::Outcome(outcome))
+    }
+}
+
+/// An outcome wins once it holds `threshold` of the active stake.
+pub struct StakeWeightedSupermajority {
+    pub threshold: f64,
+}
+
+impl ConsensusRule for StakeWeightedSupermajority {
+    fn tally(&self, ballots: &[Ballot], abstentions: &[Ballot]) -> Option<Consensus> {
+        let total: f64 = ballots.iter().chain(abstentions).map(|b| b.stake).sum();
+        weighted_plurality(ballots, total, |b| b.stake)
+            .filter(|(_, weight)| *weight / total >= self.threshold)
+            .map(|(outcome, _)| Consensus::Outcome(outcome))
+    }
+}
+
+/// Votes weighted by confidence, stake and reputation; an outcome wins with `threshold`
+/// of the total weight. Abstentions count with full confidence.
+pub struct ReputationWeighted {
+    pub threshold: f64,
+}
+
+impl ConsensusRule for ReputationWeighted {
+    fn tally(&self, ballots: &[Ballot], abstentions: &[Ballot]) -> Option<Consensus> {
+        let weight = |b: &Ballot| b.confidence * b.stake * b.reputation;
+        let total: f64 = ballots.iter().map(weight).sum::<f64>()
+            + abstentions.iter().map(|b| b.stake * b.reputation).sum::<f64>();
+        weighted_plurality(ballots, total, weight)
+            .filter(|(_, weight)| *weight / total >= self.threshold)
+            .map(|(outcome, _)| Consensus::Outcome(outcome))
+    }
+}
+
+/// Stake- and reputation-weighted median of the reported values.
+pub struct Median;
+
+impl ConsensusRule for Median {
+    fn tally(&self, ballots: &[Ballot], _abstentions: &[Ballot]) -> Option<Consensus> {
+        let mut sorted: Vec<&Ballot> = ballots.iter().collect();
+        sorted.sort_by(|a, b| a.value.total_cmp(&b.value));
+
+        let weight = |b: &Ballot| b.stake * b.reputation;
+        let total: f64 = sorted.iter().map(|b| weight(b)).sum();
+        if total <= 0.0 {
+            return None;
+        }
+
+        let mut cumulative = 0.0;
+        for ballot in sorted {
+            cumulative += weight(ballot);
+            if cumulative * 2.0 >= total {
+                return Some(Consensus::Value(ballot.value));
+            }
+        }
+        None
+    }
+}
+
+/// Mean of the reported values after dropping `trim_fraction` of ballots from each end.
+pub struct TrimmedMean {
+    pub trim_fraction: f64,
+}
+
+impl ConsensusRule for TrimmedMean {
+    fn tally(&self, ballots: &[Ballot], _abstentions: &[Ballot]) -> Option<Consensus> {
+        let mut values: Vec<f64> = ballots.iter().map(|b| b.value).collect();
+        values.sort_by(f64::total_cmp);
+
+        let trimmed = (values.len() as f64 * self.trim_fraction.clamp(0.0, 0.5)) as usize;
+        let kept = &values[trimmed..values.len() - trimmed];
+        if kept.is_empty() {
+            return None;
+        }
+        Some(Consensus::Value(kept.iter().sum::<f64>() / kept.len() as f64))
+    }
+}
+
+/// Returns the outcome with the most weight, if any weight was cast and no other
+/// outcome ties with it.
+fn weighted_plurality(
+    ballots: &[Ballot],
+    total: f64,
+    weight: impl Fn(&Ballot) -> f64,
+) -> Option<(u8, f64)> {
+    if total <= 0.0 {
+        return None;
+    }
+    let mut weights: BTreeMap<u8, f64> = BTreeMap::new();
+    for ballot in ballots {
+        *weights.entry(ballot.outcome).or_insert(0.0) += weight(ballot);
+    }
+    let (outcome, top) = weights
+        .iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(outcome, weight)| (*outcome, *weight))?;
+    if weights.values().filter(|weight| **weight == top).count() > 1 {
+        return None;
+    }
+    Some((outcome, top))
+}
+
+/// Aggregates scalar reports: takes the weighted median, rejects values further than
+/// `tolerance` (relative to the median) from it, and aggregates the rest with `rule`.
+/// Returns the aggregate and, per ballot, whether it falls inside the tolerance band.
+pub fn aggregate_scalar(
+    ballots: &[Ballot],
+    rule: &dyn ConsensusRule,
+    tolerance: f64,
+) -> Option<(f64, Vec<bool>)> {
+    let Consensus::Value(median) = Median.tally(ballots, &[])? else {
+        return None;
+    };
+    let inliers: Vec<Ballot> = ballots
+        .iter()
+        .filter(|b| within_band(b.value, median, tolerance))
+        .cloned()
+        .collect();
+    let Consensus::Value(aggregate) = rule.tally(&inliers, &[])? else {
+        return None;
+    };
+    let agreeing = ballots
+        .iter()
+        .map(|b| within_band(b.value, aggregate, tolerance))
+        .collect();
+    Some((aggregate, agreeing))
+}
+
+/// Whether `value` lies within `tolerance` of `center`, relative to `center` but never
+/// narrower than `tolerance` itself, so a center at or near zero still has a band.
+fn within_band(value: f64, center: f64, tolerance: f64) -> bool {
+    (value - center).abs() <= tolerance * center.abs().max(1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ballot(outcome: u8, stake: f64) -> Ballot {
+        Ballot {
+            outcome,
+            value: outcome as f64,
+            confidence: 1.0,
+            stake,
+            reputation: 1.0,
+        }
+    }
+
+    fn report(value: f64, stake: f64) -> Ballot {
+        Ballot {
+            value,
+            ..ballot(0, stake)
+        }
+    }
+
+    #[test]
+    fn empty_ballots_reach_no_consensus() {
+        let rules = [
+            ConsensusRuleKind::SimpleMajority,
+            ConsensusRuleKind::StakeWeightedSupermajority { threshold: 0.67 },
+            ConsensusRuleKind::ReputationWeighted,
+            ConsensusRuleKind::Median,
+            ConsensusRuleKind::TrimmedMean { trim_fraction: 0.2 },
+        ];
+        for kind in rules {
+            assert_eq!(kind.rule(0.67).tally(&[], &[]), None, "{kind:?}");
+        }
+        assert_eq!(aggregate_scalar(&[], &Median, 0.1), None);
+    }
+
+    #[test]
+    fn simple_majority_counts_abstentions() {
+        let ballots = [ballot(1, 1.0), ballot(1, 1.0), ballot(0, 1.0)];
+        assert_eq!(
+            SimpleMajority.tally(&ballots, &[]),
+            Some(Consensus::Outcome(1))
+        );
+        assert_eq!(SimpleMajority.tally(&ballots, &[ballot(0, 1.0)]), None);
+    }
+
+    #[test]
+    fn simple_majority_tie_has_no_winner() {
+        let ballots = [ballot(0, 1.0), ballot(1, 1.0)];
+        assert_eq!(SimpleMajority.tally(&ballots, &[]), None);
+    }
+
+    #[test]
+    fn supermajority_threshold_is_inclusive() {
+        let rule = StakeWeightedSupermajority { threshold: 0.75 };
+        let ballots = [ballot(1, 3.0), ballot(0, 1.0)];
+        assert_eq!(rule.tally(&ballots, &[]), Some(Consensus::Outcome(1)));
+
+        let ballots = [ballot(1, 2.9), ballot(0, 1.1)];
+        assert_eq!(rule.tally(&ballots, &[]), None);
+    }
+
+    #[test]
+    fn supermajority_counts_abstaining_stake() {
+        let rule = StakeWeightedSupermajority { threshold: 0.6 };
+        let ballots = [ballot(1, 3.0)];
+        assert_eq!(rule.tally(&ballots, &[]), Some(Consensus::Outcome(1)));
+        assert_eq!(rule.tally(&ballots, &[ballot(0, 3.0)]), None);
+    }
+
+    #[test]
+    fn supermajority_tie_has_no_winner() {
+        let rule = StakeWeightedSupermajority { threshold: 0.5 };
+        let ballots = [ballot(0, 2.0), ballot(1, 2.0)];
+        assert_eq!(rule.tally(&ballots, &[]), None);
+    }
+
+    #[test]
+    fn reputation_weighted_scales_by_confidence_and_reputation() {
+        let rule = ReputationWeighted { threshold: 0.6 };
+        let confident = Ballot {
+            reputation: 0.9,
+            ..ballot(1, 1.0)
+        };
+        let unsure = Ballot {
+            confidence: 0.2,
+            ..ballot(0, 1.0)
+        };
+        assert_eq!(
+            rule.tally(&[confident.clone(), unsure], &[]),
+            Some(Consensus::Outcome(1))
+        );
+
+        let distrusted = Ballot {
+            reputation: 0.1,
+            ..ballot(1, 1.0)
+        };
+        assert_eq!(
+            rule.tally(&[distrusted, ballot(0, 1.0)], &[]),
+            Some(Consensus::Outcome(0))
+        );
+        assert_eq!(rule.tally(&[confident], &[ballot(0, 1.0)]), None);
+    }
+
+    #[test]
+    fn median_is_stake_weighted() {
+        let ballots = [report(10.0, 1.0), report(20.0, 1.0), report(30.0, 5.0)];
+        assert_eq!(Median.tally(&ballots, &[]), Some(Consensus::Value(30.0)));
+
+        let ballots = [report(10.0, 1.0), report(20.0, 1.0), report(30.0, 1.0)];
+        assert_eq!(Median.tally(&ballots, &[]), Some(Consensus::Value(20.0)));
+    }
+
+    #[test]
+    fn median_without_weight_has_no_value() {
+        let ballots = [report(10.0, 0.0), report(20.0, 0.0)];
+        assert_eq!(Median.tally(&ballots, &[]), None);
+    }
+
+    #[test]
+    fn trimmed_mean_drops_both_tails() {
+        let rule = TrimmedMean { trim_fraction: 0.2 };
+        let ballots = [
+            report(-100.0, 1.0),
+            report(10.0, 1.0),
+            report(20.0, 1.0),
+            report(30.0, 1.0),
+            report(1000.0, 1.0),
+        ];
+        assert_eq!(rule.tally(&ballots, &[]), Some(Consensus::Value(20.0)));
+    }
+
+    #[test]
+    fn trimmed_mean_trimming_everything_has_no_value() {
+        let rule = TrimmedMean { trim_fraction: 0.5 };
+        let ballots = [report(10.0, 1.0), report(20.0, 1.0)];
+        assert_eq!(rule.tally(&ballots, &[]), None);
+    }
+
+    #[test]
+    fn scalar_aggregate_rejects_outliers() {
+        let ballots = [
+            report(100.0, 1.0),
+            report(101.0, 1.0),
+            report(99.0, 1.0),
+            report(500.0, 1.0),
+        ];
+        let (aggregate, agreeing) = aggregate_scalar(&ballots, &Median, 0.05).unwrap();
+        assert_eq!(aggregate, 100.0);
+        assert_eq!(agreeing, vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn scalar_aggregate_uses_the_rule_on_inliers() {
+        let ballots = [
+            report(99.0, 1.0),
+            report(100.0, 1.0),
+            report(101.0, 1.0),
+            report(102.0, 1.0),
+            report(500.0, 1.0),
+        ];
+        let (median, _) = aggregate_scalar(&ballots, &Median, 0.05).unwrap();
+        assert_eq!(median, 100.0);
+
+        let rule = TrimmedMean { trim_fraction: 0.2 };
+        let (mean, agreeing) = aggregate_scalar(&ballots, &rule, 0.05).unwrap();
+        assert_eq!(mean, 100.5);
+        assert_eq!(agreeing, vec![true, true, true, true, false]);
+    }
+
+    #[test]
+    fn scalar_band_has_an_absolute_floor() {
+        let ballots = [
+            report(0.0, 1.0),
+            report(0.0, 1.0),
+            report(0.04, 1.0),
+            report(3.0, 1.0),
+        ];
+        let (aggregate, agreeing) = aggregate_scalar(&ballots, &Median, 0.05).unwrap();
+        assert_eq!(aggregate, 0.0);
+        assert_eq!(agreeing, vec![true, true, true, false]);
+    }
+
+    #[test]
+    fn value_based_rules_are_flagged() {
+        assert!(ConsensusRuleKind::Median.is_value_based());
+        assert!(ConsensusRuleKind::TrimmedMean { trim_fraction: 0.1 }.is_value_based());
+        assert!(!ConsensusRuleKind::SimpleMajority.is_value_based());
+        assert!(!ConsensusRuleKind::ReputationWeighted.is_value_based());
+    }
+}
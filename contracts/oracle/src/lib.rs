@@ -1,18 +1,64 @@
+pub mod consensus;
+
+use consensus::{aggregate_scalar, Ballot, Consensus, ConsensusRule, ConsensusRuleKind, Median};
 use linera_sdk::{
-    base::{ApplicationId, ChainId, Owner},
-    views::{linera_views, MapView, RegisterView, RootView, ViewStorageContext},
+    base::{
+        crypto::{BcsHashable, CryptoHash},
+        Account, Amount, ApplicationId, ChainId, GenericApplicationId, Owner, StreamName, StreamUpdate,
+    },
+    views::{linera_views, MapView, RegisterView, RootView, View, ViewStorageContext},
     Contract, ContractRuntime, Service, ServiceRuntime,
 };
+use async_graphql::{EmptyMutation, EmptySubscription, Object, Schema};
+use prediction_market::{
+    MarketEvent, Operation as MarketOperation, PredictionMarketAbi, RESOLUTION_REQUESTS_STREAM,
+};
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 use thiserror::Error;
 
+/// Parts-per-million scale used for stake shares.
+const PPM: u128 = 1_000_000;
+
+/// Stake as a vote weight, in whole tokens.
+fn stake_weight(stake: Amount) -> f64 {
+    u128::from(stake) as f64 / u128::from(Amount::ONE) as f64
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct OracleParameters {
     pub required_consensus: f64,
     pub min_oracles: u8,
+    /// Native tokens an oracle must bond to register, escrowed on the registry chain.
+    pub min_stake: Amount,
+    /// Fraction of stake taken from oracles that vote against the consensus outcome.
+    pub slash_fraction: f64,
+    /// Microseconds between deactivation and stake withdrawal.
+    pub unbonding_delay: u64,
+    /// Microseconds after the first commitment during which oracles may commit.
+    pub commit_duration: u64,
+    /// Microseconds after the commit window during which oracles may reveal.
+    pub reveal_duration: u64,
+    /// Chain keeping the global oracle set. Defaults to each chain acting as its own registry.
+    pub registry_chain: Option<ChainId>,
+    /// Rule used to turn revealed votes into a final outcome. The value-based `Median` and
+    /// `TrimmedMean` rules aggregate scalar reports, and discrete requests fail under them;
+    /// scalar reports take the median under the other rules.
+    #[serde(default)]
+    pub consensus_rule: ConsensusRuleKind,
+    /// Relative band around the median inside which scalar reports agree with it, and at
+    /// least this much in absolute terms so a median near zero keeps a band. Must be
+    /// positive for scalar requests to be served.
+    #[serde(default)]
+    pub value_tolerance: f64,
+    /// Prediction market application whose resolution requests the oracle network serves,
+    /// and to which it reports results.
+    #[serde(default)]
+    pub market_app_id: Option<ApplicationId>,
 }
 
-#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+#[serde(default)]
 pub struct OracleNode {
     pub name: String,
     pub data_source: String,
@@ -20,22 +66,163 @@ pub struct OracleNode {
     pub total_votes: u64,
     pub is_active: bool,
     pub registered_at: u64,
+    pub stake: Amount,
+    pub unbonding_until: Option<u64>,
+    pub correct_votes: u64,
+    pub scored_votes: u64,
+    pub brier_sum: f64,
+    /// One minus the mean Brier score of finalised votes. Multiplies vote weight. Scores,
+    /// like the stake, are kept by the registry and only mirrored on the oracle's chain.
+    pub reputation: f64,
+}
+
+impl Default for OracleNode {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            data_source: String::new(),
+            accuracy: 100.0,
+            total_votes: 0,
+            is_active: false,
+            registered_at: 0,
+            stake: Amount::ZERO,
+            unbonding_until: None,
+            correct_votes: 0,
+            scored_votes: 0,
+            brier_sum: 0.0,
+            reputation: 1.0,
+        }
+    }
+}
+
+impl OracleNode {
+    /// Records a finalised vote. The Brier score treats `confidence` as the probability
+    /// the oracle gave to the outcome it voted for.
+    pub fn record_scored_vote(&mut self, correct: bool, confidence: f64) {
+        let target = if correct { 1.0 } else { 0.0 };
+        self.brier_sum += (confidence - target).powi(2);
+        self.scored_votes += 1;
+        if correct {
+            self.correct_votes += 1;
+        }
+        self.accuracy = 100.0 * self.correct_votes as f64 / self.scored_votes as f64;
+        self.reputation = 1.0 - self.brier_sum / self.scored_votes as f64;
+    }
+}
+
+/// A numeric report, `value` scaled by `10^decimals`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct ScalarValue {
+    pub value: i64,
+    pub decimals: u8,
+    pub unit: String,
+}
+
+impl ScalarValue {
+    pub fn as_f64(&self) -> f64 {
+        self.value as f64 / 10f64.powi(self.decimals as i32)
+    }
+    
+    pub fn from_f64(value: f64, decimals: u8, unit: String) -> Self {
+        ScalarValue {
+            value: (value * 10f64.powi(decimals as i32)).round() as i64,
+            decimals,
+            unit,
+        }
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct VoteRecord {
     pub oracle_id: ChainId,
     pub outcome: u8,
+    pub value: Option<ScalarValue>,
     pub confidence: f64,
+    /// Data source the oracle attests it read the result from, and the hash of that data.
+    pub source: String,
     pub data_hash: String,
+    pub stake: Amount,
+    pub reputation: f64,
     pub timestamp: u64,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct VoteCommit {
+    pub oracle_id: ChainId,
+    pub commitment_hash: String,
+    pub stake: Amount,
+    pub reputation: f64,
+    pub revealed: bool,
+}
+
+/// Votes on a market, tallied on the market chain. Only revealed votes are counted.
 #[derive(Clone, Debug, Default, Deserialize, Serialize)]
 pub struct MarketVotes {
+    pub commits: Vec<VoteCommit>,
     pub votes: Vec<VoteRecord>,
+    pub commit_deadline: Option<u64>,
+    pub reveal_deadline: Option<u64>,
     pub resolved: bool,
     pub final_outcome: Option<u8>,
+    pub final_value: Option<ScalarValue>,
+}
+
+/// A market of the prediction market application: its chain and its id there.
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize,
+    async_graphql::SimpleObject, async_graphql::InputObject,
+)]
+#[graphql(input_name = "MarketRefInput")]
+pub struct MarketRef {
+    pub chain_id: ChainId,
+    pub market_id: u64,
+}
+
+/// A market's request for resolution. Discrete markets list their `options`; scalar markets
+/// leave them empty and report values in `unit`, scaled by `10^decimals`. Commitments are
+/// accepted until `deadline`.
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct ResolutionRequest {
+    pub market: MarketRef,
+    pub question: String,
+    pub options: Vec<String>,
+    pub unit: Option<String>,
+    pub decimals: u8,
+    pub deadline: u64,
+}
+
+/// Preimage of a vote commitment. `confidence` is hashed through its bit pattern. The
+/// voting oracle and the market are included so a commitment cannot be replayed elsewhere.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct VoteCommitment {
+    pub oracle_id: ChainId,
+    pub market: MarketRef,
+    pub outcome: u8,
+    pub value: Option<ScalarValue>,
+    pub confidence_bits: u64,
+    pub salt: String,
+}
+
+impl BcsHashable for VoteCommitment {}
+
+/// Commitment hash an oracle submits with `CommitVote` before revealing its vote.
+pub fn vote_commitment(
+    oracle_id: ChainId,
+    market: MarketRef,
+    outcome: u8,
+    value: Option<&ScalarValue>,
+    confidence: f64,
+    salt: &str,
+) -> String {
+    CryptoHash::new(&VoteCommitment {
+        oracle_id,
+        market,
+        outcome,
+        value: value.cloned(),
+        confidence_bits: confidence.to_bits(),
+        salt: salt.to_string(),
+    })
+    .to_string()
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -43,33 +230,130 @@ pub enum Operation {
     RegisterOracle {
         name: String,
         data_source: String,
+        stake: Amount,
     },
-    SubmitVote {
-        market_chain: ChainId,
+    CommitVote {
+        market: MarketRef,
+        commitment_hash: String,
+    },
+    /// Reveals a committed vote. Scalar markets report `value`; `outcome` is ignored for them.
+    RevealVote {
+        market: MarketRef,
         outcome: u8,
+        value: Option<ScalarValue>,
         confidence: f64,
+        salt: String,
+        source: String,
         data_hash: String,
     },
+    /// Subscribes the registry to the resolution requests the market application publishes
+    /// on `chain_id`.
+    WatchMarketChain {
+        chain_id: ChainId,
+    },
+    /// Tallies the revealed votes of a market on its chain once its reveal window has closed.
+    FinalizeVotes {
+        market: MarketRef,
+    },
     DeactivateOracle,
     ReactivateOracle,
+    WithdrawStake,
+    /// Subscribes this chain to oracle membership updates from the registry chain.
+    SubscribeToRegistry,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Message {
-    VoteSubmitted {
+    /// Counted with the oracle's stake and reputation as recorded by the registry.
+    VoteCommitted {
+        oracle: ChainId,
+        market: MarketRef,
+        commitment_hash: String,
+    },
+    VoteRevealed {
         oracle: ChainId,
-        market: ChainId,
+        market: MarketRef,
         outcome: u8,
+        value: Option<ScalarValue>,
         confidence: f64,
+        salt: String,
+        source: String,
+        data_hash: String,
     },
+    /// Delivers the aggregate to the market chain: `outcome` for discrete votes,
+    /// `value` for scalar reports.
     ConsensusReached {
-        market: ChainId,
-        outcome: u8,
+        market: MarketRef,
+        outcome: Option<u8>,
+        value: Option<ScalarValue>,
         total_votes: u64,
     },
+    /// Sent by the registry to subscribed chains and the market chain once the market
+    /// application has requested a resolution.
+    ResolutionRequest {
+        request: ResolutionRequest,
+    },
+    /// Sent by the market chain to the registry once voting on a request has closed, with or
+    /// without consensus, and forwarded by the registry to its subscribers.
+    RequestClosed {
+        market: MarketRef,
+    },
+    /// Sent to the market chain when voting closed without consensus, or by the registry
+    /// when the request could not be opened.
+    ResolutionFailed {
+        market: MarketRef,
+        total_votes: u64,
+    },
+    /// Sent by an oracle chain to the registry with the bond it transferred there.
     OracleRegistered {
         oracle: ChainId,
         name: String,
+        data_source: String,
+        stake: Amount,
+    },
+    /// Sent by an oracle chain to the registry to start unbonding.
+    DeactivationRequested {
+        oracle: ChainId,
+    },
+    ReactivationRequested {
+        oracle: ChainId,
+    },
+    /// Sent by an oracle chain to the registry to have its unbonded stake paid to `owner`.
+    WithdrawalRequested {
+        oracle: ChainId,
+        owner: Option<Owner>,
+    },
+    /// Sent by the registry to an oracle chain with its current record.
+    OracleRecordUpdated {
+        node: OracleNode,
+    },
+    RegistrySubscription {
+        subscriber: ChainId,
+    },
+    /// Sent by the registry to subscribed chains.
+    MembershipUpdated {
+        oracle: ChainId,
+        node: OracleNode,
+    },
+    MembershipSnapshot {
+        oracles: Vec<(ChainId, OracleNode)>,
+    },
+    /// Sent by the market chain to the registry once voting closes: the bonds of `dissenters`
+    /// are slashed in favour of `agreeing`. When voting fails, the oracles that never
+    /// revealed are slashed in favour of those that did.
+    StakeSlashed {
+        market: MarketRef,
+        dissenters: Vec<ChainId>,
+        agreeing: Vec<ChainId>,
+    },
+    /// Sent by the market chain to the registry for every oracle that committed on a
+    /// finalised market. The registry keeps accuracy and reputation.
+    VoteScored {
+        market: MarketRef,
+        oracle: ChainId,
+        revealed: bool,
+        correct: bool,
+        confidence: f64,
     },
 }
 
@@ -85,14 +369,36 @@ pub enum OracleError {
     InvalidConfidence,
     #[error("Unauthorized")]
     Unauthorized,
+    #[error("Stake below the required minimum")]
+    InsufficientStake,
+    #[error("Stake is still bonded")]
+    StillBonded,
+    #[error("No vote committed on this market")]
+    NoCommitment,
+    #[error("Revealed vote does not match the commitment")]
+    CommitmentMismatch,
+    #[error("Voting on this market is still open")]
+    VotingOpen,
+    #[error("No open resolution request for this market")]
+    UnknownRequest,
+    #[error("Resolution request has expired")]
+    RequestExpired,
+    #[error("Outcome is not one of the requested options")]
+    InvalidOutcome,
+    #[error("No prediction market application configured")]
+    NoMarketApp,
 }
 
 #[derive(RootView, async_graphql::SimpleObject)]
 #[view(context = "ViewStorageContext")]
 pub struct OracleView {
     pub oracle_info: RegisterView<OracleNode>,
-    pub market_votes: MapView<ChainId, MarketVotes>,
+    pub market_votes: MapView<MarketRef, MarketVotes>,
+    pub submitted_commits: MapView<MarketRef, String>,
+    pub submitted_votes: MapView<MarketRef, VoteRecord>,
     pub registered_oracles: MapView<ChainId, OracleNode>,
+    pub registry_subscribers: MapView<ChainId, u64>,
+    pub resolution_requests: MapView<MarketRef, ResolutionRequest>,
 }
 
 pub struct OracleContract {
@@ -108,91 +414,221 @@ impl Contract for OracleContract {
         Self { runtime }
     }
 
-    async fn instantiate(&mut self, node: Self::InstantiationArgument) {
+    async fn instantiate(&mut self, mut node: Self::InstantiationArgument) {
+        // Stake is only ever bonded by `RegisterOracle`.
+        node.stake = Amount::ZERO;
         self.runtime.state().oracle_info.set(node);
     }
 
     async fn execute_operation(&mut self, operation: Operation) -> Result<(), OracleError> {
         match operation {
-            Operation::RegisterOracle { name, data_source } => {
-                let oracle = OracleNode {
-                    name: name.clone(),
-                    data_source,
-                    accuracy: 100.0,
-                    total_votes: 0,
-                    is_active: true,
-                    registered_at: self.runtime.system_time().micros(),
-                };
-                
-                self.runtime.state().oracle_info.set(oracle.clone());
+            Operation::RegisterOracle { name, data_source, stake } => {
+                let params = self.runtime.application_parameters();
+                // Re-registering tops up the bond and keeps the oracle's track record.
+                let oracle = self.runtime.state().oracle_info.get().clone();
+                if oracle.stake.saturating_add(stake) < params.min_stake {
+                    return Err(OracleError::InsufficientStake);
+                }
                 
-                let chain_id = self.runtime.chain_id();
-                self.runtime.state().registered_oracles.insert(&chain_id, oracle);
+                // The bond is escrowed on the registry chain, out of the operator's reach, and
+                // slashed there. This chain's record is updated once the registry has it.
+                let registry = self.registry_chain();
+                let signer = self.runtime.authenticated_signer();
+                self.runtime.transfer(signer, Account::chain(registry), stake);
                 
                 self.runtime.prepare_message(Message::OracleRegistered {
-                    oracle: chain_id,
+                    oracle: self.runtime.chain_id(),
                     name,
-                }).send_to(chain_id);
+                    data_source,
+                    stake,
+                }).send_to(registry);
                 
                 Ok(())
             }
             
-            Operation::SubmitVote { market_chain, outcome, confidence, data_hash } => {
+            Operation::CommitVote { market, commitment_hash } => {
+                let oracle_info = self.runtime.state().oracle_info.get();
+                if !oracle_info.is_active {
+                    return Err(OracleError::Inactive);
+                }
+                
+                let request = self.runtime.state().resolution_requests.get(&market).await
+                    .unwrap_or_default()
+                    .ok_or(OracleError::UnknownRequest)?;
+                if self.runtime.system_time().micros() >= request.deadline {
+                    return Err(OracleError::RequestExpired);
+                }
+                
+                let already_committed = self.runtime.state().submitted_commits.get(&market).await
+                    .unwrap_or_default()
+                    .is_some();
+                if already_committed {
+                    return Err(OracleError::AlreadyVoted);
+                }
+                
+                self.runtime.state().submitted_commits.insert(&market, commitment_hash.clone());
+                
+                // Votes are tallied by the oracle application on the market chain.
+                self.runtime.prepare_message(Message::VoteCommitted {
+                    oracle: self.runtime.chain_id(),
+                    market,
+                    commitment_hash,
+                }).send_to(market.chain_id);
+                
+                Ok(())
+            }
+            
+            Operation::RevealVote { market, outcome, value, confidence, salt, source, data_hash } => {
                 if !(0.0..=1.0).contains(&confidence) {
                     return Err(OracleError::InvalidConfidence);
                 }
                 
-                let oracle_info = self.runtime.state().oracle_info.get();
-                if !oracle_info.is_active {
-                    return Err(OracleError::Inactive);
+                let commitment = self.runtime.state().submitted_commits.get(&market).await
+                    .unwrap_or_default()
+                    .ok_or(OracleError::NoCommitment)?;
+                let oracle_id = self.runtime.chain_id();
+                if commitment != vote_commitment(oracle_id, market, outcome, value.as_ref(), confidence, &salt) {
+                    return Err(OracleError::CommitmentMismatch);
                 }
                 
-                let mut market_votes = self.runtime.state().market_votes.get(&market_chain).await
+                let request = self.runtime.state().resolution_requests.get(&market).await
                     .unwrap_or_default()
-                    .unwrap_or_default();
+                    .ok_or(OracleError::UnknownRequest)?;
+                if value.is_none() && outcome as usize >= request.options.len() {
+                    return Err(OracleError::InvalidOutcome);
+                }
                 
-                let oracle_chain = self.runtime.chain_id();
-                if market_votes.votes.iter().any(|v| v.oracle_id == oracle_chain) {
+                let already_revealed = self.runtime.state().submitted_votes.get(&market).await
+                    .unwrap_or_default()
+                    .is_some();
+                if already_revealed {
                     return Err(OracleError::AlreadyVoted);
                 }
                 
-                market_votes.votes.push(VoteRecord {
+                let oracle_info = self.runtime.state().oracle_info.get().clone();
+                let oracle_chain = self.runtime.chain_id();
+                let vote = VoteRecord {
                     oracle_id: oracle_chain,
                     outcome,
+                    value: value.clone(),
                     confidence,
-                    data_hash,
+                    source: source.clone(),
+                    data_hash: data_hash.clone(),
+                    stake: oracle_info.stake,
+                    reputation: oracle_info.reputation,
                     timestamp: self.runtime.system_time().micros(),
-                });
-                
-                self.runtime.state().market_votes.insert(&market_chain, market_votes.clone());
-                
-                let mut oracle_info = oracle_info.clone();
-                oracle_info.total_votes += 1;
-                self.runtime.state().oracle_info.set(oracle_info);
+                };
+                self.runtime.state().submitted_votes.insert(&market, vote);
                 
-                self.runtime.prepare_message(Message::VoteSubmitted {
+                self.runtime.prepare_message(Message::VoteRevealed {
                     oracle: oracle_chain,
-                    market: market_chain,
+                    market,
                     outcome,
+                    value,
                     confidence,
-                }).send_to(market_chain);
+                    salt,
+                    source,
+                    data_hash,
+                }).send_to(market.chain_id);
+                
+                Ok(())
+            }
+            
+            Operation::FinalizeVotes { market } => {
+                if self.runtime.chain_id() != market.chain_id {
+                    return Err(OracleError::Unauthorized);
+                }
+                let market_votes = self.runtime.state().market_votes.get(&market).await
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                let request = self.runtime.state().resolution_requests.get(&market).await
+                    .unwrap_or_default()
+                    .ok_or(OracleError::UnknownRequest)?;
+                
+                // Voting is over once reveals close, or at the deadline if nobody committed.
+                let now = self.runtime.system_time().micros();
+                let closes_at = market_votes.reveal_deadline.unwrap_or(request.deadline);
+                if now < closes_at {
+                    return Err(OracleError::VotingOpen);
+                }
                 
-                self.check_consensus(market_chain, &market_votes).await;
+                if !market_votes.resolved && !self.check_consensus(market, &market_votes).await {
+                    self.runtime.state().resolution_requests.remove(&market);
+                    
+                    // Withholding a reveal can sink the vote, so it is penalised here as well,
+                    // in favour of the oracles that did reveal.
+                    let revealed: Vec<ChainId> = market_votes.votes.iter()
+                        .map(|vote| vote.oracle_id)
+                        .collect();
+                    self.slash_dissenters(market, &market_votes, &revealed);
+                    self.score_unrevealed(market, &market_votes);
+                    self.close_request(market);
+                    
+                    self.runtime.prepare_message(Message::ResolutionFailed {
+                        market,
+                        total_votes: market_votes.votes.len() as u64,
+                    }).send_to(market.chain_id);
+                }
                 
                 Ok(())
             }
             
+            // Membership changes are applied by the registry, which holds the bonds.
             Operation::DeactivateOracle => {
-                let mut oracle_info = self.runtime.state().oracle_info.get().clone();
-                oracle_info.is_active = false;
-                self.runtime.state().oracle_info.set(oracle_info);
+                let oracle = self.runtime.chain_id();
+                let registry = self.registry_chain();
+                self.runtime.prepare_message(Message::DeactivationRequested { oracle }).send_to(registry);
                 Ok(())
             }
             
             Operation::ReactivateOracle => {
-                let mut oracle_info = self.runtime.state().oracle_info.get().clone();
-                oracle_info.is_active = true;
-                self.runtime.state().oracle_info.set(oracle_info);
+                let params = self.runtime.application_parameters();
+                if self.runtime.state().oracle_info.get().stake < params.min_stake {
+                    return Err(OracleError::InsufficientStake);
+                }
+                let oracle = self.runtime.chain_id();
+                let registry = self.registry_chain();
+                self.runtime.prepare_message(Message::ReactivationRequested { oracle }).send_to(registry);
+                Ok(())
+            }
+            
+            Operation::WithdrawStake => {
+                let oracle_info = self.runtime.state().oracle_info.get().clone();
+                let now = self.runtime.system_time().micros();
+                match oracle_info.unbonding_until {
+                    Some(until) if !oracle_info.is_active && now >= until => {}
+                    _ => return Err(OracleError::StillBonded),
+                }
+                
+                let oracle = self.runtime.chain_id();
+                let owner = self.runtime.authenticated_signer();
+                let registry = self.registry_chain();
+                self.runtime.prepare_message(Message::WithdrawalRequested {
+                    oracle,
+                    owner,
+                }).send_to(registry);
+                Ok(())
+            }
+            
+            Operation::WatchMarketChain { chain_id } => {
+                if self.runtime.chain_id() != self.registry_chain() {
+                    return Err(OracleError::Unauthorized);
+                }
+                let market_app = self.runtime.application_parameters()
+                    .market_app_id
+                    .ok_or(OracleError::NoMarketApp)?;
+                self.runtime.subscribe_to_events(chain_id, market_app, StreamName(RESOLUTION_REQUESTS_STREAM.to_vec()));
+                // Votes are weighted on the market chain with the registry's records.
+                self.add_subscriber(chain_id).await;
+                Ok(())
+            }
+            
+            Operation::SubscribeToRegistry => {
+                let subscriber = self.runtime.chain_id();
+                let registry = self.registry_chain();
+                self.runtime.prepare_message(Message::RegistrySubscription {
+                    subscriber,
+                }).send_to(registry);
                 Ok(())
             }
         }
@@ -200,58 +636,843 @@ impl Contract for OracleContract {
 
     async fn execute_message(&mut self, message: Self::Message) {
         match message {
-            Message::VoteSubmitted { oracle, market, outcome, confidence } => {
+            Message::VoteCommitted { oracle, market, commitment_hash } => {
+                let params = self.runtime.application_parameters();
+                if self.runtime.message_id().map(|id| id.chain_id) != Some(oracle) {
+                    return;
+                }
+                
+                // Only active members vote, weighted by the record the registry keeps for them.
+                let population = self.active_oracles().await;
+                let Some((_, node)) = population.iter().find(|(id, _)| *id == oracle) else {
+                    return;
+                };
+                let (stake, reputation) = (node.stake, node.reputation);
+                
+                let Ok(Some(request)) = self.runtime.state().resolution_requests.get(&market).await else {
+                    return;
+                };
+                
+                let mut market_votes = self.runtime.state().market_votes.get(&market).await
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                
+                // The commit window never extends past the request deadline.
+                let now = self.runtime.system_time().micros();
+                let commit_deadline = *market_votes.commit_deadline
+                    .get_or_insert((now + params.commit_duration).min(request.deadline));
+                market_votes.reveal_deadline = Some(commit_deadline + params.reveal_duration);
+                
+                if market_votes.resolved
+                    || now >= commit_deadline
+                    || market_votes.commits.iter().any(|c| c.oracle_id == oracle)
+                {
+                    return;
+                }
+                
+                market_votes.commits.push(VoteCommit {
+                    oracle_id: oracle,
+                    commitment_hash,
+                    stake,
+                    reputation,
+                    revealed: false,
+                });
+                
+                self.runtime.state().market_votes.insert(&market, market_votes);
+            }
+            Message::VoteRevealed { oracle, market, outcome, value, confidence, salt, source, data_hash } => {
+                if self.runtime.message_id().map(|id| id.chain_id) != Some(oracle) {
+                    return;
+                }
+                let Ok(Some(request)) = self.runtime.state().resolution_requests.get(&market).await else {
+                    return;
+                };
+                if value.is_none() && outcome as usize >= request.options.len() {
+                    return;
+                }
+                
+                let mut market_votes = self.runtime.state().market_votes.get(&market).await
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                
+                let now = self.runtime.system_time().micros();
+                let in_reveal_window = matches!(
+                    (market_votes.commit_deadline, market_votes.reveal_deadline),
+                    (Some(start), Some(end)) if now >= start && now < end
+                );
+                if market_votes.resolved || !in_reveal_window {
+                    return;
+                }
+                
+                let Some(commit) = market_votes.commits.iter_mut()
+                    .find(|c| c.oracle_id == oracle && !c.revealed)
+                else {
+                    return;
+                };
+                if commit.commitment_hash != vote_commitment(oracle, market, outcome, value.as_ref(), confidence, &salt) {
+                    return;
+                }
+                commit.revealed = true;
+                let stake = commit.stake;
+                let reputation = commit.reputation;
+                
+                market_votes.votes.push(VoteRecord {
+                    oracle_id: oracle,
+                    outcome,
+                    value,
+                    confidence,
+                    source,
+                    data_hash,
+                    stake,
+                    reputation,
+                    timestamp: now,
+                });
+                
+                self.runtime.state().market_votes.insert(&market, market_votes.clone());
+                
+                if market_votes.commits.iter().all(|c| c.revealed) {
+                    self.check_consensus(market, &market_votes).await;
+                }
+            }
+            Message::ConsensusReached { market, outcome, value, .. } => {
+                // Sent by `check_consensus` on the market chain to itself.
+                let origin = self.runtime.message_id().map(|id| id.chain_id);
+                if origin != Some(market.chain_id) || self.runtime.chain_id() != market.chain_id {
+                    return;
+                }
+                self.notify_market(MarketOperation::OracleResolved {
+                    market_id: market.market_id,
+                    outcome,
+                    value: value.map(|value| value.value),
+                });
+            }
+            Message::OracleRegistered { oracle, name, data_source, stake } => {
+                if !self.from_member(oracle) {
+                    return;
+                }
+                let params = self.runtime.application_parameters();
+                let mut node = self.runtime.state().registered_oracles.get(&oracle).await
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                
+                if node.registered_at == 0 {
+                    node.registered_at = self.runtime.system_time().micros();
+                }
+                node.name = name;
+                node.data_source = data_source;
+                node.stake = node.stake.saturating_add(stake);
+                node.is_active = node.stake >= params.min_stake;
+                node.unbonding_until = None;
+                self.publish_member(oracle, node).await;
+            }
+            Message::DeactivationRequested { oracle } => {
+                if !self.from_member(oracle) {
+                    return;
+                }
+                let Ok(Some(mut node)) = self.runtime.state().registered_oracles.get(&oracle).await else {
+                    return;
+                };
+                let params = self.runtime.application_parameters();
+                node.is_active = false;
+                node.unbonding_until = Some(self.runtime.system_time().micros() + params.unbonding_delay);
+                self.publish_member(oracle, node).await;
+            }
+            Message::ReactivationRequested { oracle } => {
+                if !self.from_member(oracle) {
+                    return;
+                }
+                let Ok(Some(mut node)) = self.runtime.state().registered_oracles.get(&oracle).await else {
+                    return;
+                };
+                let params = self.runtime.application_parameters();
+                if node.stake < params.min_stake {
+                    return;
+                }
+                node.is_active = true;
+                node.unbonding_until = None;
+                self.publish_member(oracle, node).await;
+            }
+            Message::WithdrawalRequested { oracle, owner } => {
+                if !self.from_member(oracle) {
+                    return;
+                }
+                let Ok(Some(mut node)) = self.runtime.state().registered_oracles.get(&oracle).await else {
+                    return;
+                };
+                let now = self.runtime.system_time().micros();
+                match node.unbonding_until {
+                    Some(until) if !node.is_active && now >= until => {}
+                    _ => return,
+                }
+                
+                self.runtime.transfer(None, Account { chain_id: oracle, owner }, node.stake);
+                node.stake = Amount::ZERO;
+                node.unbonding_until = None;
+                self.publish_member(oracle, node).await;
+            }
+            Message::OracleRecordUpdated { node } => {
+                if !self.from_registry() {
+                    return;
+                }
+                self.runtime.state().oracle_info.set(node);
+            }
+            Message::ResolutionRequest { request } => {
+                // Requests are opened by the registry, which reads them from the market application.
+                if !self.from_registry() {
+                    return;
+                }
+                let market = request.market;
+                let known = self.runtime.state().resolution_requests.contains_key(&market).await
+                    .unwrap_or(true);
+                if known {
+                    return;
+                }
+                self.runtime.state().resolution_requests.insert(&market, request);
+            }
+            Message::ResolutionFailed { market, .. } => {
+                let origin = self.runtime.message_id().map(|id| id.chain_id);
+                let registry = self.registry_chain();
+                if origin != Some(market.chain_id) && origin != Some(registry) {
+                    return;
+                }
+                self.runtime.state().resolution_requests.remove(&market);
+                
+                if self.runtime.chain_id() == market.chain_id {
+                    self.notify_market(MarketOperation::OracleFailed { market_id: market.market_id });
+                }
+            }
+            Message::RequestClosed { market } => {
+                let origin = self.runtime.message_id().map(|id| id.chain_id);
+                let registry = self.registry_chain();
+                if self.runtime.chain_id() == registry && origin == Some(market.chain_id) {
+                    let subscribers = self.runtime.state().registry_subscribers.indices().await
+                        .unwrap_or_default();
+                    for subscriber in subscribers {
+                        if subscriber != registry && subscriber != market.chain_id {
+                            self.runtime.prepare_message(Message::RequestClosed { market }).send_to(subscriber);
+                        }
+                    }
+                } else if !self.from_registry() {
+                    return;
+                }
+                self.runtime.state().resolution_requests.remove(&market);
+            }
+            Message::RegistrySubscription { subscriber } => {
+                if self.runtime.chain_id() != self.registry_chain() {
+                    return;
+                }
+                self.add_subscriber(subscriber).await;
             }
-            Message::ConsensusReached { market, outcome, total_votes } => {
+            Message::MembershipUpdated { oracle, node } => {
+                if !self.from_registry() {
+                    return;
+                }
+                self.runtime.state().registered_oracles.insert(&oracle, node);
+            }
+            Message::MembershipSnapshot { oracles } => {
+                if !self.from_registry() {
+                    return;
+                }
+                for (oracle, node) in oracles {
+                    self.runtime.state().registered_oracles.insert(&oracle, node);
+                }
+            }
+            Message::StakeSlashed { market, dissenters, agreeing } => {
+                // Reported by the oracle application on the market chain, which tallied the votes.
+                let origin = self.runtime.message_id().map(|id| id.chain_id);
+                if self.runtime.chain_id() != self.registry_chain() || origin != Some(market.chain_id) {
+                    return;
+                }
+                self.settle_stakes(&dissenters, &agreeing).await;
+            }
+            Message::VoteScored { market, oracle, revealed, correct, confidence } => {
+                // Reported by the oracle application on the market chain, which tallied the votes.
+                let origin = self.runtime.message_id().map(|id| id.chain_id);
+                if self.runtime.chain_id() != self.registry_chain() || origin != Some(market.chain_id) {
+                    return;
+                }
+                let Ok(Some(mut node)) = self.runtime.state().registered_oracles.get(&oracle).await else {
+                    return;
+                };
+                node.record_scored_vote(correct, confidence);
+                if revealed {
+                    node.total_votes += 1;
+                }
+                self.publish_member(oracle, node).await;
             }
-            Message::OracleRegistered { oracle, name } => {
+        }
+    }
+    
+    async fn process_streams(&mut self, updates: Vec<StreamUpdate>) {
+        if self.runtime.chain_id() != self.registry_chain() {
+            return;
+        }
+        let Some(market_app) = self.runtime.application_parameters().market_app_id else {
+            return;
+        };
+        
+        let requests_stream = StreamName(RESOLUTION_REQUESTS_STREAM.to_vec());
+        for update in updates {
+            // Only the market application's own stream can open requests for its markets.
+            if update.stream_id.application_id != GenericApplicationId::User(market_app)
+                || update.stream_id.stream_name != requests_stream
+            {
+                continue;
+            }
+            for index in update.new_indices() {
+                let event: MarketEvent = self.runtime.read_event(
+                    update.chain_id,
+                    requests_stream.clone(),
+                    index,
+                );
+                if let MarketEvent::ResolutionRequested(query) = event {
+                    self.open_request(ResolutionRequest {
+                        market: MarketRef {
+                            chain_id: update.chain_id,
+                            market_id: query.market_id,
+                        },
+                        question: query.question,
+                        options: query.options,
+                        unit: query.unit,
+                        decimals: query.decimals,
+                        deadline: query.deadline,
+                    }).await;
+                }
             }
         }
     }
 }
 
 impl OracleContract {
-    async fn check_consensus(&mut self, market: ChainId, votes: &MarketVotes) {
+    fn registry_chain(&mut self) -> ChainId {
+        self.runtime.application_parameters()
+            .registry_chain
+            .unwrap_or_else(|| self.runtime.chain_id())
+    }
+    
+    /// Whether the message being executed was sent by the registry chain.
+    fn from_registry(&mut self) -> bool {
+        let registry = self.registry_chain();
+        self.runtime.message_id().map(|id| id.chain_id) == Some(registry)
+    }
+    
+    /// Whether this chain is the registry and the message being executed was sent by `oracle`.
+    fn from_member(&mut self, oracle: ChainId) -> bool {
+        self.runtime.chain_id() == self.registry_chain()
+            && self.runtime.message_id().map(|id| id.chain_id) == Some(oracle)
+    }
+    
+    /// Adds a chain to the registry's subscribers and sends it the current oracle set.
+    async fn add_subscriber(&mut self, subscriber: ChainId) {
+        let now = self.runtime.system_time().micros();
+        self.runtime.state().registry_subscribers.insert(&subscriber, now);
+        
+        let mut oracles = Vec::new();
+        for oracle in self.runtime.state().registered_oracles.indices().await.unwrap_or_default() {
+            if let Ok(Some(node)) = self.runtime.state().registered_oracles.get(&oracle).await {
+                oracles.push((oracle, node));
+            }
+        }
+        self.runtime.prepare_message(Message::MembershipSnapshot {
+            oracles,
+        }).send_to(subscriber);
+    }
+    
+    /// Stores an oracle's record on the registry and sends it to the subscribed chains and
+    /// to the oracle's own chain.
+    async fn publish_member(&mut self, oracle: ChainId, node: OracleNode) {
+        self.runtime.state().registered_oracles.insert(&oracle, node.clone());
+        
+        let subscribers = self.runtime.state().registry_subscribers.indices().await
+            .unwrap_or_default();
+        for subscriber in subscribers {
+            self.runtime.prepare_message(Message::MembershipUpdated {
+                oracle,
+                node: node.clone(),
+            }).send_to(subscriber);
+        }
+        self.runtime.prepare_message(Message::OracleRecordUpdated { node }).send_to(oracle);
+    }
+    
+    /// Takes `slash_fraction` of each dissenter's bond and pays it to the `agreeing` oracles
+    /// in proportion to their own bonds. When no bonded oracle agreed, the slashed stake stays
+    /// in the registry's escrow.
+    async fn settle_stakes(&mut self, dissenters: &[ChainId], agreeing: &[ChainId]) {
         let params = self.runtime.application_parameters();
+        let mut winners = Vec::new();
+        for oracle in agreeing {
+            if let Ok(Some(node)) = self.runtime.state().registered_oracles.get(oracle).await {
+                winners.push((*oracle, node));
+            }
+        }
+        let winning_stake: u128 = winners.iter()
+            .map(|(_, node)| u128::from(node.stake))
+            .fold(0, u128::saturating_add);
+        let slash_ppm = (params.slash_fraction.clamp(0.0, 1.0) * PPM as f64) as u128;
         
-        if votes.votes.len() < params.min_oracles as usize {
+        let mut slashed = 0u128;
+        for oracle in dissenters {
+            let Ok(Some(mut node)) = self.runtime.state().registered_oracles.get(oracle).await else {
+                continue;
+            };
+            let penalty = u128::from(node.stake).saturating_mul(slash_ppm) / PPM;
+            node.stake = node.stake.saturating_sub(Amount::from_attos(penalty));
+            // Slashed below the minimum, an oracle stops voting until it tops up its bond.
+            node.is_active &= node.stake >= params.min_stake;
+            slashed += penalty;
+            self.publish_member(*oracle, node).await;
+        }
+        
+        if winning_stake == 0 {
+            return;
+        }
+        for (oracle, mut node) in winners {
+            // Share in parts per million, so the product stays within u128.
+            let share_ppm = u128::from(node.stake).saturating_mul(PPM) / winning_stake;
+            let reward = Amount::from_attos(slashed.saturating_mul(share_ppm) / PPM);
+            node.stake = node.stake.saturating_add(reward);
+            self.publish_member(oracle, node).await;
+        }
+    }
+    
+    /// Opens a request on the registry and forwards it to the subscribed chains and to the
+    /// market chain, where votes are tallied. A request that cannot be voted on fails
+    /// straight away.
+    async fn open_request(&mut self, request: ResolutionRequest) {
+        let market = request.market;
+        let known = self.runtime.state().resolution_requests.contains_key(&market).await
+            .unwrap_or(true);
+        if known {
             return;
         }
         
-        let mut vote_weights: std::collections::HashMap<u8, f64> = std::collections::HashMap::new();
+        // Scalar requests need a tolerance band to score reports against, and discrete ones
+        // a rule that picks an outcome.
+        let params = self.runtime.application_parameters();
+        let votable = match request.unit {
+            Some(_) => params.value_tolerance > 0.0,
+            None => !params.consensus_rule.is_value_based(),
+        };
+        if !votable || request.deadline <= self.runtime.system_time().micros() {
+            self.runtime.prepare_message(Message::ResolutionFailed {
+                market,
+                total_votes: 0,
+            }).send_to(market.chain_id);
+            return;
+        }
         
-        for vote in &votes.votes {
-            *vote_weights.entry(vote.outcome).or_insert(0.0) += vote.confidence;
+        self.runtime.state().resolution_requests.insert(&market, request.clone());
+        
+        let mut recipients = self.runtime.state().registry_subscribers.indices().await
+            .unwrap_or_default();
+        if !recipients.contains(&market.chain_id) {
+            recipients.push(market.chain_id);
+        }
+        let registry = self.runtime.chain_id();
+        for recipient in recipients.into_iter().filter(|chain| *chain != registry) {
+            self.runtime.prepare_message(Message::ResolutionRequest {
+                request: request.clone(),
+            }).send_to(recipient);
+        }
+    }
+    
+    /// Reports an oracle result to the prediction market application on this chain.
+    fn notify_market(&mut self, operation: MarketOperation) {
+        let Some(market_app) = self.runtime.application_parameters().market_app_id else {
+            return;
+        };
+        self.runtime.call_application(true, market_app.with_abi::<PredictionMarketAbi>(), &operation);
+    }
+    
+    /// Active members of the oracle set, as last received from the registry.
+    async fn active_oracles(&mut self) -> Vec<(ChainId, OracleNode)> {
+        let mut oracles = Vec::new();
+        for oracle in self.runtime.state().registered_oracles.indices().await.unwrap_or_default() {
+            if let Ok(Some(node)) = self.runtime.state().registered_oracles.get(&oracle).await {
+                if node.is_active {
+                    oracles.push((oracle, node));
+                }
+            }
         }
+        oracles
+    }
+    
+    /// Resolves the market if its revealed votes reach consensus. Returns whether it did.
+    async fn check_consensus(&mut self, market: MarketRef, votes: &MarketVotes) -> bool {
+        let params = self.runtime.application_parameters();
+        let quorum = params.min_oracles as usize;
         
-        let total_weight: f64 = vote_weights.values().sum();
+        let Ok(Some(request)) = self.runtime.state().resolution_requests.get(&market).await else {
+            return false;
+        };
         
-        for (outcome, weight) in vote_weights {
-            if weight / total_weight >= params.required_consensus {
-                self.runtime.prepare_message(Message::ConsensusReached {
-                    market,
-                    outcome,
-                    total_votes: votes.votes.len() as u64,
-                }).send_to(market);
-                
-                break;
+        let ballots: Vec<Ballot> = votes.votes.iter()
+            .map(|vote| Ballot {
+                outcome: vote.outcome,
+                value: vote.value.as_ref().map_or(vote.outcome as f64, ScalarValue::as_f64),
+                confidence: vote.confidence,
+                stake: stake_weight(vote.stake),
+                reputation: vote.reputation,
+            })
+            .collect();
+        
+        let (outcome, value, agreeing) = if let Some(unit) = &request.unit {
+            // Scalar reports with outlier rejection. Reports in another unit are outliers.
+            let reported: Vec<(ChainId, Ballot)> = votes.votes.iter()
+                .zip(ballots)
+                .filter(|(vote, _)| vote.value.as_ref().is_some_and(|v| &v.unit == unit))
+                .map(|(vote, ballot)| (vote.oracle_id, ballot))
+                .collect();
+            if reported.len() < quorum {
+                return false;
+            }
+            let scalar_ballots: Vec<Ballot> = reported.iter().map(|(_, ballot)| ballot.clone()).collect();
+            // Value-based rules aggregate the inliers; outcome rules fall back to their median.
+            let rule: Box<dyn ConsensusRule> = if params.consensus_rule.is_value_based() {
+                params.consensus_rule.rule(params.required_consensus)
+            } else {
+                Box::new(Median)
+            };
+            let Some((aggregate, in_band)) = aggregate_scalar(&scalar_ballots, rule.as_ref(), params.value_tolerance) else {
+                return false;
+            };
+            
+            let agreeing: Vec<ChainId> = reported.iter()
+                .zip(in_band)
+                .filter(|(_, in_band)| *in_band)
+                .map(|((oracle, _), _)| *oracle)
+                .collect();
+            (None, Some(ScalarValue::from_f64(aggregate, request.decimals, unit.clone())), agreeing)
+        } else {
+            // Averaging outcome indices does not pick an outcome; the request fails instead.
+            if params.consensus_rule.is_value_based() || votes.votes.len() < quorum {
+                return false;
             }
+            
+            // Active oracles that did not vote count as abstentions against every outcome.
+            let abstentions: Vec<Ballot> = self.active_oracles().await.iter()
+                .filter(|(id, _)| !votes.votes.iter().any(|v| v.oracle_id == *id))
+                .map(|(_, node)| Ballot {
+                    outcome: 0,
+                    value: 0.0,
+                    confidence: 1.0,
+                    stake: stake_weight(node.stake),
+                    reputation: node.reputation,
+                })
+                .collect();
+            
+            let rule = params.consensus_rule.rule(params.required_consensus);
+            let outcome = match rule.tally(&ballots, &abstentions) {
+                Some(Consensus::Outcome(outcome)) => outcome,
+                Some(Consensus::Value(_)) | None => return false,
+            };
+            let agreeing: Vec<ChainId> = votes.votes.iter()
+                .filter(|v| v.outcome == outcome)
+                .map(|v| v.oracle_id)
+                .collect();
+            (Some(outcome), None, agreeing)
+        };
+        
+        let mut votes = votes.clone();
+        votes.resolved = true;
+        votes.final_outcome = outcome;
+        votes.final_value = value.clone();
+        self.runtime.state().market_votes.insert(&market, votes.clone());
+        self.runtime.state().resolution_requests.remove(&market);
+        
+        self.runtime.prepare_message(Message::ConsensusReached {
+            market,
+            outcome,
+            value,
+            total_votes: votes.votes.len() as u64,
+        }).send_to(market.chain_id);
+        
+        self.slash_dissenters(market, &votes, &agreeing);
+        self.score_votes(market, &votes, &agreeing);
+        self.close_request(market);
+        true
+    }
+    
+    /// Reports to the registry whether each committed oracle's vote agreed with consensus.
+    /// Unrevealed commitments are scored as fully confident wrong votes.
+    fn score_votes(&mut self, market: MarketRef, votes: &MarketVotes, agreeing: &[ChainId]) {
+        let registry = self.registry_chain();
+        for vote in &votes.votes {
+            self.runtime.prepare_message(Message::VoteScored {
+                market,
+                oracle: vote.oracle_id,
+                revealed: true,
+                correct: agreeing.contains(&vote.oracle_id),
+                confidence: vote.confidence,
+            }).send_to(registry);
+        }
+        self.score_unrevealed(market, votes);
+    }
+    
+    /// Scores every commitment that was never revealed as a fully confident wrong vote.
+    fn score_unrevealed(&mut self, market: MarketRef, votes: &MarketVotes) {
+        let registry = self.registry_chain();
+        for commit in votes.commits.iter().filter(|c| !c.revealed) {
+            self.runtime.prepare_message(Message::VoteScored {
+                market,
+                oracle: commit.oracle_id,
+                revealed: false,
+                correct: false,
+                confidence: 1.0,
+            }).send_to(registry);
         }
     }
+    
+    /// Tells the registry that voting on a request is over, so it and the chains it sent
+    /// the request to stop tracking it.
+    fn close_request(&mut self, market: MarketRef) {
+        let registry = self.registry_chain();
+        self.runtime.prepare_message(Message::RequestClosed { market }).send_to(registry);
+    }
+    
+    /// Asks the registry, which holds the bonds, to slash every oracle outside `agreeing` or
+    /// that never revealed its commitment in favour of the agreeing oracles.
+    fn slash_dissenters(&mut self, market: MarketRef, votes: &MarketVotes, agreeing: &[ChainId]) {
+        let dissenters = votes.votes.iter()
+            .filter(|v| !agreeing.contains(&v.oracle_id))
+            .map(|v| v.oracle_id)
+            .chain(votes.commits.iter()
+                .filter(|c| !c.revealed)
+                .map(|c| c.oracle_id))
+            .collect();
+        
+        let registry = self.registry_chain();
+        self.runtime.prepare_message(Message::StakeSlashed {
+            market,
+            dissenters,
+            agreeing: agreeing.to_vec(),
+        }).send_to(registry);
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct LeaderboardEntry {
+    pub rank: u32,
+    pub oracle: ChainId,
+    pub name: String,
+    pub reputation: f64,
+    pub accuracy: f64,
+    pub scored_votes: u64,
+    pub stake: Amount,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct RegisteredOracle {
+    pub oracle: ChainId,
+    pub node: OracleNode,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct OutcomeTally {
+    pub outcome: u8,
+    pub votes: u32,
+    pub weight: f64,
+}
+
+/// Voting state of a market. Weights are confidence × stake × reputation, and
+/// `consensus_ratio` is the leading outcome's share of the revealed weight.
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct MarketVotesSummary {
+    pub market: MarketRef,
+    pub commits: u32,
+    pub revealed: u32,
+    pub tallies: Vec<OutcomeTally>,
+    pub consensus_ratio: f64,
+    pub commit_deadline: Option<u64>,
+    pub reveal_deadline: Option<u64>,
+    pub resolved: bool,
+    pub final_outcome: Option<u8>,
+    pub final_value: Option<ScalarValue>,
+}
+
+/// A revealed vote. `correct` is filled in for votes tallied on this chain once a discrete
+/// market has resolved.
+#[derive(Clone, Debug, Deserialize, Serialize, async_graphql::SimpleObject)]
+pub struct VoteHistoryEntry {
+    pub market: MarketRef,
+    pub outcome: u8,
+    pub value: Option<ScalarValue>,
+    pub confidence: f64,
+    pub source: String,
+    pub data_hash: String,
+    pub timestamp: u64,
+    pub correct: Option<bool>,
 }
 
 pub struct OracleService {
-    runtime: ServiceRuntime<Self>,
+    state: Arc<OracleView>,
 }
 
 impl Service for OracleService {
     type Parameters = OracleParameters;
 
     async fn load(runtime: ServiceRuntime<Self>) -> Self {
-        Self { runtime }
+        let state = OracleView::load(runtime.root_view_storage_context())
+            .await
+            .expect("Failed to load state");
+        Self {
+            state: Arc::new(state),
+        }
     }
 
     async fn handle_query(&self, query: Self::Query) -> Self::QueryResponse {
-        query
+        let schema = Schema::build(
+            OracleQueryRoot {
+                state: self.state.clone(),
+            },
+            EmptyMutation,
+            EmptySubscription,
+        )
+        .finish();
+        schema.execute(query).await
+    }
+}
+
+struct OracleQueryRoot {
+    state: Arc<OracleView>,
+}
+
+#[Object]
+impl OracleQueryRoot {
+    async fn oracle_info(&self) -> OracleNode {
+        self.state.oracle_info.get().clone()
+    }
+    
+    /// Oracles known to this chain's registry, optionally filtered by `active`.
+    async fn registered_oracles(&self, active: Option<bool>) -> Vec<RegisteredOracle> {
+        let mut oracles = Vec::new();
+        for oracle in self.state.registered_oracles.indices().await.unwrap_or_default() {
+            if let Ok(Some(node)) = self.state.registered_oracles.get(&oracle).await {
+                if active.map_or(true, |active| node.is_active == active) {
+                    oracles.push(RegisteredOracle { oracle, node });
+                }
+            }
+        }
+        oracles
+    }
+    
+    async fn market_votes(&self, market: MarketRef) -> Option<MarketVotesSummary> {
+        let votes = self.state.market_votes.get(&market).await.ok()??;
+        
+        let mut tallies: Vec<OutcomeTally> = Vec::new();
+        for vote in &votes.votes {
+            let weight = vote.confidence * stake_weight(vote.stake) * vote.reputation;
+            match tallies.iter_mut().find(|t| t.outcome == vote.outcome) {
+                Some(tally) => {
+                    tally.votes += 1;
+                    tally.weight += weight;
+                }
+                None => tallies.push(OutcomeTally {
+                    outcome: vote.outcome,
+                    votes: 1,
+                    weight,
+                }),
+            }
+        }
+        tallies.sort_by_key(|t| t.outcome);
+        
+        let total_weight: f64 = tallies.iter().map(|t| t.weight).sum();
+        let leading_weight = tallies.iter().map(|t| t.weight).fold(0.0, f64::max);
+        let consensus_ratio = if total_weight > 0.0 { leading_weight / total_weight } else { 0.0 };
+        
+        Some(MarketVotesSummary {
+            market,
+            commits: votes.commits.len() as u32,
+            revealed: votes.votes.len() as u32,
+            tallies,
+            consensus_ratio,
+            commit_deadline: votes.commit_deadline,
+            reveal_deadline: votes.reveal_deadline,
+            resolved: votes.resolved,
+            final_outcome: votes.final_outcome,
+            final_value: votes.final_value,
+        })
+    }
+    
+    /// Resolution requests that have not yet been resolved or failed.
+    async fn pending_requests(&self) -> Vec<ResolutionRequest> {
+        let mut requests = Vec::new();
+        for market in self.state.resolution_requests.indices().await.unwrap_or_default() {
+            if let Ok(Some(request)) = self.state.resolution_requests.get(&market).await {
+                requests.push(request);
+            }
+        }
+        requests.sort_by_key(|r| r.deadline);
+        requests
+    }
+    
+    /// Votes revealed by `oracle` on markets tallied on this chain, or this chain's own
+    /// votes when `oracle` is omitted.
+    async fn voting_history(&self, oracle: Option<ChainId>) -> Vec<VoteHistoryEntry> {
+        let mut history = Vec::new();
+        match oracle {
+            None => {
+                for market in self.state.submitted_votes.indices().await.unwrap_or_default() {
+                    let Ok(Some(vote)) = self.state.submitted_votes.get(&market).await else {
+                        continue;
+                    };
+                    history.push(VoteHistoryEntry {
+                        market,
+                        outcome: vote.outcome,
+                        value: vote.value,
+                        confidence: vote.confidence,
+                        source: vote.source,
+                        data_hash: vote.data_hash,
+                        timestamp: vote.timestamp,
+                        correct: None,
+                    });
+                }
+            }
+            Some(oracle) => {
+                for market in self.state.market_votes.indices().await.unwrap_or_default() {
+                    let Ok(Some(votes)) = self.state.market_votes.get(&market).await else {
+                        continue;
+                    };
+                    let final_outcome = votes.final_outcome;
+                    for vote in votes.votes.into_iter().filter(|v| v.oracle_id == oracle) {
+                        history.push(VoteHistoryEntry {
+                            market,
+                            outcome: vote.outcome,
+                            correct: final_outcome.map(|outcome| outcome == vote.outcome),
+                            value: vote.value,
+                            confidence: vote.confidence,
+                            source: vote.source,
+                            data_hash: vote.data_hash,
+                            timestamp: vote.timestamp,
+                        });
+                    }
+                }
+            }
+        }
+        history.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+        history
+    }
+    
+    async fn oracle_leaderboard(&self) -> Vec<LeaderboardEntry> {
+        let mut oracles = Vec::new();
+        for chain_id in self.state.registered_oracles.indices().await.unwrap_or_default() {
+            if let Ok(Some(node)) = self.state.registered_oracles.get(&chain_id).await {
+                oracles.push((chain_id, node));
+            }
+        }
+        oracles.sort_by(|(_, a), (_, b)| b.reputation.total_cmp(&a.reputation));
+        
+        oracles.into_iter()
+            .enumerate()
+            .map(|(i, (oracle, node))| LeaderboardEntry {
+                rank: i as u32 + 1,
+                oracle,
+                name: node.name,
+                reputation: node.reputation,
+                accuracy: node.accuracy,
+                scored_votes: node.scored_votes,
+                stake: node.stake,
+            })
+            .collect()
     }
 }